@@ -0,0 +1,84 @@
+//! Interrupt-driven ADT75 temperature reads on the REB1 board using RTIC
+//!
+//! The millisecond tick starts a temperature read every second. The I2C transfer runs in the
+//! I2CA interrupt handler, and the idle task prints the result without ever waiting for the bus.
+#![no_main]
+#![no_std]
+
+#[rtic::app(device = pac)]
+mod app {
+    use panic_rtt_target as _;
+    use rtt_target::{rprintln, rtt_init_print};
+    use va108xx_hal::{
+        pac,
+        prelude::*,
+        timer::{default_ms_irq_handler, set_up_ms_timer, IrqCfg},
+    };
+    use vorago_reb1::{temp_sensor::Adt75TempSensor, temp_sensor_irq::Adt75IrqReader};
+
+    const SAMPLE_PERIOD_MS: u32 = 1000;
+
+    #[local]
+    struct Local {}
+
+    #[shared]
+    struct Shared {
+        reader: Adt75IrqReader,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+        rprintln!("-- Vorago Interrupt-Driven Temperature Sensor Example --");
+        let mut dp = ctx.device;
+        let temp_sensor = Adt75TempSensor::new_on_board(dp.I2CA, 50.mhz(), Some(&mut dp.SYSCONFIG))
+            .expect("Creating temperature sensor struct failed");
+        // Route the I2CA interrupt to interrupt vector 1
+        let reader = Adt75IrqReader::new(
+            temp_sensor,
+            IrqCfg::new(pac::interrupt::OC1, true, true),
+            Some(&mut dp.SYSCONFIG),
+            Some(&mut dp.IRQSEL),
+        );
+        set_up_ms_timer(
+            IrqCfg::new(pac::Interrupt::OC0, true, true),
+            &mut dp.SYSCONFIG,
+            Some(&mut dp.IRQSEL),
+            50.mhz(),
+            dp.TIM0,
+        );
+        (Shared { reader }, Local {}, init::Monotonics())
+    }
+
+    #[idle(shared = [reader])]
+    fn idle(mut cx: idle::Context) -> ! {
+        loop {
+            match cx.shared.reader.lock(|reader| reader.poll()) {
+                Ok(temp) => rprintln!("Temperature in Celcius: {}", temp),
+                Err(nb::Error::Other(e)) => rprintln!("Reading the temperature failed: {:?}", e),
+                Err(nb::Error::WouldBlock) => (),
+            }
+            // The millisecond tick interrupt wakes up the CPU regularly
+            cortex_m::asm::wfi();
+        }
+    }
+
+    #[task(binds = OC1, shared = [reader])]
+    fn i2ca_task(mut cx: i2ca_task::Context) {
+        cx.shared.reader.lock(|reader| reader.on_interrupt());
+    }
+
+    #[task(binds = OC0, local = [ticks: u32 = 0], shared = [reader])]
+    fn ms_tick(mut cx: ms_tick::Context) {
+        default_ms_irq_handler();
+        *cx.local.ticks += 1;
+        if *cx.local.ticks == SAMPLE_PERIOD_MS {
+            *cx.local.ticks = 0;
+            cx.shared.reader.lock(|reader| {
+                if reader.start().is_err() {
+                    rprintln!("Previous temperature read still in progress");
+                }
+            });
+        }
+    }
+}
@@ -11,16 +11,21 @@ fn main() -> ! {
     rtt_init_print!();
     rprintln!("-- Vorago Temperature Sensor and I2C Example --");
     let mut dp = pac::Peripherals::take().unwrap();
-    let mut delay = set_up_ms_delay_provider(&mut dp.SYSCONFIG, 50.mhz(), dp.TIM0);
+    let syscfg = &mut dp.SYSCONFIG;
+    let mut delay = set_up_ms_delay_provider(syscfg, 50.mhz(), dp.TIM0);
     unsafe {
         cortex_m::peripheral::NVIC::unmask(pac::Interrupt::OC0);
     }
 
-    let mut temp_sensor = Adt75TempSensor::new(dp.I2CA, 50.mhz(), Some(&mut dp.SYSCONFIG))
+    let mut temp_sensor = Adt75TempSensor::new_on_board(dp.I2CA, 50.mhz(), Some(syscfg))
         .expect("Creating temperature sensor struct failed");
+    // The sensor only powers up for a single conversion in every loop iteration
+    temp_sensor
+        .enter_one_shot_mode()
+        .expect("Enabling the one-shot mode failed");
     loop {
         let temp = temp_sensor
-            .read_temperature()
+            .read_one_shot(&mut delay)
             .expect("Failed reading temperature");
         rprintln!("Temperature in Celcius: {}", temp);
         delay.delay_ms(500_u16);
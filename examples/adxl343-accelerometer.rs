@@ -15,13 +15,7 @@ use va108xx_hal::{
     spi::{Spi, SpiConfig, TransferConfig},
     timer::set_up_ms_delay_provider,
 };
-
-const READ_MASK: u8 = 1 << 7;
-const _MULTI_BYTE_MASK: u8 = 1 << 6;
-const DEVID_REG: u8 = 0x00;
-
-const POWER_CTL_REG: u8 = 0x2D;
-const PWR_MEASUREMENT_MODE_MASK: u8 = 1 << 3;
+use vorago_reb1::{adxl343::Adxl343, sensors::Accelerometer};
 
 #[entry]
 fn main() -> ! {
@@ -45,7 +39,7 @@ fn main() -> ! {
         .expect("Setting ADC chip select high failed");
 
     let transfer_cfg = TransferConfig::new(1.mhz(), spi::MODE_3, Some(cs_pin), false, true);
-    let mut spi = Spi::spib(
+    let spi = Spi::spib(
         dp.SPIB,
         (sck, miso, mosi),
         50.mhz(),
@@ -54,20 +48,12 @@ fn main() -> ! {
         Some(&transfer_cfg.downgrade()),
     );
 
-    let mut send_buf: [u8; 3] = [0; 3];
-    send_buf[0] = READ_MASK | DEVID_REG;
-    let reply = spi
-        .transfer(&mut send_buf[0..2])
-        .expect("Reading DEVID register failed");
-    rprintln!("DEVID register: {}", reply[1]);
-
-    send_buf[0] = POWER_CTL_REG;
-    send_buf[1] = PWR_MEASUREMENT_MODE_MASK;
-    spi.write(&send_buf[0..2])
-        .expect("Enabling measurement mode failed");
-
+    let mut accelerometer = Adxl343::new(spi).expect("Creating ADXL343 driver failed");
     loop {
+        let accel = accelerometer
+            .read_acceleration()
+            .expect("Reading acceleration failed");
+        rprintln!("Acceleration: X {}, Y {}, Z {}", accel.x, accel.y, accel.z);
         delay.delay_ms(500_u16);
-        unimplemented!("Not implemented for now, is not populated on the board..");
     }
 }
@@ -9,15 +9,13 @@ use cortex_m_rt::entry;
 use panic_rtt_target as _;
 use rtt_target::{rprintln, rtt_init_print};
 use va108xx_hal::{
-    clock::{set_clk_div_register, FilterClkSel},
-    gpio::{FilterType, InterruptEdge, PinsA},
+    gpio::{InterruptEdge, PinsA},
     pac::{self, interrupt},
     prelude::*,
-    time::Hertz,
     timer::{default_ms_irq_handler, set_up_ms_timer, IrqCfg},
 };
 use vorago_reb1::button::Button;
-use vorago_reb1::leds::Leds;
+use vorago_reb1::leds::{LedId, Leds};
 
 static LEDS: Mutex<RefCell<Option<Leds>>> = Mutex::new(RefCell::new(None));
 static BUTTON: Mutex<RefCell<Option<Button>>> = Mutex::new(RefCell::new(None));
@@ -52,12 +50,9 @@ fn main() -> ! {
 
     if PRESS_MODE == PressMode::Toggle {
         // This filter debounces the switch for edge based interrupts
-        button = button.filter_type(FilterType::FilterFourClockCycles, FilterClkSel::Clk1);
-        set_clk_div_register(
-            &mut dp.SYSCONFIG,
-            FilterClkSel::Clk1,
-            Hertz::from(50.khz()).0,
-        );
+        button = button
+            .debounced(4000.us(), 50.mhz(), &mut dp.SYSCONFIG)
+            .expect("configuring the button debounce filter failed");
     }
 
     set_up_ms_timer(
@@ -80,7 +75,10 @@ fn main() -> ! {
         LEDS.borrow(cs).replace(Some(leds));
         BUTTON.borrow(cs).replace(Some(button));
     });
-    loop {}
+    loop {
+        // Sleep until the next interrupt
+        cortex_m::asm::wfi();
+    }
 }
 
 #[interrupt]
@@ -92,18 +90,18 @@ fn OC0() {
 fn OC15() {
     cortex_m::interrupt::free(|cs| {
         if PRESS_MODE == PressMode::Toggle {
-            if let Some(ref mut leds) = LEDS.borrow(cs).borrow_mut().as_deref_mut() {
-                leds[0].toggle();
+            if let Some(leds) = LEDS.borrow(cs).borrow_mut().as_mut() {
+                leds[LedId::Ld2].toggle();
             }
         } else {
-            if let (Some(ref mut leds), Some(ref button)) = (
-                LEDS.borrow(cs).borrow_mut().as_deref_mut(),
+            if let (Some(leds), Some(button)) = (
+                LEDS.borrow(cs).borrow_mut().as_mut(),
                 BUTTON.borrow(cs).borrow().as_ref(),
             ) {
                 if button.released() {
-                    leds[0].off();
+                    leds[LedId::Ld2].off();
                 } else {
-                    leds[0].on();
+                    leds[LedId::Ld2].on();
                 }
             }
         }
@@ -7,15 +7,13 @@ mod app {
     use panic_rtt_target as _;
     use rtt_target::{rprintln, rtt_init_default, set_print_channel};
     use va108xx_hal::{
-        clock::{set_clk_div_register, FilterClkSel},
-        gpio::{FilterType, InterruptEdge, PinsA},
+        gpio::{InterruptEdge, PinsA},
         pac,
         prelude::*,
-        time::Hertz,
         timer::{default_ms_irq_handler, set_up_ms_timer, IrqCfg},
     };
     use vorago_reb1::button::Button;
-    use vorago_reb1::leds::Leds;
+    use vorago_reb1::leds::{LedId, Leds};
 
     #[derive(Debug, PartialEq)]
     pub enum PressMode {
@@ -73,12 +71,9 @@ mod app {
 
         if mode == PressMode::Toggle {
             // This filter debounces the switch for edge based interrupts
-            button = button.filter_type(FilterType::FilterFourClockCycles, FilterClkSel::Clk1);
-            set_clk_div_register(
-                &mut dp.SYSCONFIG,
-                FilterClkSel::Clk1,
-                Hertz::from(50.khz()).0,
-            );
+            button = button
+                .debounced(4000.us(), 50.mhz(), &mut dp.SYSCONFIG)
+                .expect("configuring the button debounce filter failed");
         }
         let mut leds = Leds::new(
             pinsa.pa10.into_push_pull_output(),
@@ -101,7 +96,10 @@ mod app {
     // `shared` cannot be accessed from this context
     #[idle]
     fn idle(_cx: idle::Context) -> ! {
-        loop {}
+        loop {
+            // Sleep until the next interrupt
+            cortex_m::asm::wfi();
+        }
     }
 
     #[task(binds = OC15, local=[button, leds, mode])]
@@ -110,12 +108,12 @@ mod app {
         let button = cx.local.button;
         let mode = cx.local.mode;
         if *mode == PressMode::Toggle {
-            leds[0].toggle();
+            leds[LedId::Ld2].toggle();
         } else {
             if button.released() {
-                leds[0].off();
+                leds[LedId::Ld2].off();
             } else {
-                leds[0].on();
+                leds[LedId::Ld2].on();
             }
         }
     }
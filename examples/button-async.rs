@@ -0,0 +1,102 @@
+//! Async button example for the REB1 board
+//!
+//! The board logic is written as an async function which waits for the button. A minimal
+//! executor runs the future and sleeps with WFI until the button interrupt wakes it up.
+#![no_main]
+#![no_std]
+
+use core::{
+    future::Future,
+    pin::pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use va108xx_hal::{
+    gpio::{InterruptEdge, PinsA},
+    pac::{self, interrupt},
+    prelude::*,
+    timer::IrqCfg,
+};
+use vorago_reb1::{
+    button::Button,
+    leds::{LedId, Leds},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    rprintln!("-- Vorago Async Button Example --");
+    let mut dp = pac::Peripherals::take().unwrap();
+    let pinsa = PinsA::new(&mut dp.SYSCONFIG, Some(dp.IOCONFIG), dp.PORTA);
+    let button = Button::new(pinsa.pa11.into_floating_input())
+        .edge_irq(
+            InterruptEdge::BothEdges,
+            IrqCfg::new(pac::interrupt::OC15, true, true),
+            Some(&mut dp.SYSCONFIG),
+            Some(&mut dp.IRQSEL),
+        )
+        .debounced(4000.us(), 50.mhz(), &mut dp.SYSCONFIG)
+        .expect("configuring the button debounce filter failed");
+    let mut leds = Leds::new(
+        pinsa.pa10.into_push_pull_output(),
+        pinsa.pa7.into_push_pull_output(),
+        pinsa.pa6.into_push_pull_output(),
+    );
+    for led in leds.iter_mut() {
+        led.off();
+    }
+    block_on(async {
+        let mut presses: u32 = 0;
+        loop {
+            button.wait_for_press().await;
+            presses += 1;
+            rprintln!("Button pressed {} times", presses);
+            leds[LedId::Ld2].on();
+            button.wait_for_release().await;
+            leds[LedId::Ld2].off();
+            leds[LedId::Ld4].toggle();
+        }
+    })
+}
+
+#[interrupt]
+fn OC15() {
+    Button::on_interrupt();
+}
+
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+fn wake(_: *const ()) {
+    WOKEN.store(true, Ordering::Release);
+}
+
+/// Poll the future whenever it was woken and sleep with WFI otherwise
+fn block_on<F: Future>(fut: F) -> F::Output {
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |_| RawWaker::new(core::ptr::null(), &VTABLE),
+        wake,
+        wake,
+        |_| (),
+    );
+    // Safety: The vtable functions do not access the data pointer
+    let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) };
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = pin!(fut);
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+        // Interrupts are masked so a wakeup between the check and WFI is not lost. A pending
+        // interrupt still ends the sleep and is serviced after the critical section.
+        cortex_m::interrupt::free(|_| {
+            if !WOKEN.load(Ordering::Acquire) {
+                cortex_m::asm::wfi();
+            }
+            WOKEN.store(false, Ordering::Release);
+        });
+    }
+}
@@ -0,0 +1,127 @@
+//! Button event queue example for the REB1 board using RTIC
+//!
+//! The button interrupt only pushes timestamped edges into a lock-free queue. The idle task
+//! drains the queue, debounces the edges and toggles LEDs depending on the detected gesture.
+#![no_main]
+#![no_std]
+
+#[rtic::app(device = pac)]
+mod app {
+    use panic_rtt_target as _;
+    use rtt_target::{rprintln, rtt_init_print};
+    use va108xx_hal::{
+        gpio::{InterruptEdge, PinsA},
+        pac,
+        prelude::*,
+        timer::{default_ms_irq_handler, get_ms_ticks, set_up_ms_timer, IrqCfg},
+    };
+    use vorago_reb1::{
+        button::Button,
+        debouncer::{ButtonEvent, DebounceConfig, Debouncer},
+        event_queue::{Consumer, EdgeEvent, EventQueue, Producer},
+        leds::{LedId, Leds},
+    };
+
+    const QUEUE_DEPTH: usize = 16;
+
+    #[local]
+    struct Local {
+        leds: Leds,
+        button: Button,
+        producer: Producer<'static, EdgeEvent, QUEUE_DEPTH>,
+        consumer: Consumer<'static, EdgeEvent, QUEUE_DEPTH>,
+    }
+
+    #[shared]
+    struct Shared {}
+
+    #[init(local = [queue: EventQueue<EdgeEvent, QUEUE_DEPTH> = EventQueue::new()])]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        rtt_init_print!();
+        rprintln!("-- Vorago Button Event Queue Example --");
+        let mut dp = ctx.device;
+        let pinsa = PinsA::new(&mut dp.SYSCONFIG, Some(dp.IOCONFIG), dp.PORTA);
+
+        // Configure an edge interrupt on the button and route it to interrupt vector 15
+        let button = Button::new(pinsa.pa11.into_floating_input()).edge_irq(
+            InterruptEdge::BothEdges,
+            IrqCfg::new(pac::interrupt::OC15, true, true),
+            Some(&mut dp.SYSCONFIG),
+            Some(&mut dp.IRQSEL),
+        );
+        let mut leds = Leds::new(
+            pinsa.pa10.into_push_pull_output(),
+            pinsa.pa7.into_push_pull_output(),
+            pinsa.pa6.into_push_pull_output(),
+        );
+        for led in leds.iter_mut() {
+            led.off();
+        }
+        set_up_ms_timer(
+            IrqCfg::new(pac::Interrupt::OC0, true, true),
+            &mut dp.SYSCONFIG,
+            Some(&mut dp.IRQSEL),
+            50.mhz(),
+            dp.TIM0,
+        );
+        let (producer, consumer) = ctx.local.queue.split();
+        (
+            Shared {},
+            Local {
+                leds,
+                button,
+                producer,
+                consumer,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[idle(local = [leds, consumer])]
+    fn idle(cx: idle::Context) -> ! {
+        let leds = cx.local.leds;
+        let consumer = cx.local.consumer;
+        let mut debouncer = Debouncer::new(DebounceConfig::default());
+        let mut reported_overflows = 0;
+        loop {
+            for edge in consumer.drain() {
+                for event in debouncer.update(edge.pressed, edge.timestamp_ms) {
+                    handle_event(leds, event);
+                }
+            }
+            for event in debouncer.poll(get_ms_ticks()) {
+                handle_event(leds, event);
+            }
+            if consumer.overflows() != reported_overflows {
+                reported_overflows = consumer.overflows();
+                rprintln!("Button event queue overflows: {}", reported_overflows);
+            }
+            // The millisecond tick interrupt wakes up the CPU regularly
+            cortex_m::asm::wfi();
+        }
+    }
+
+    fn handle_event(leds: &mut Leds, event: ButtonEvent) {
+        rprintln!("{:?}", event);
+        match event {
+            ButtonEvent::ShortClick => leds[LedId::Ld2].toggle(),
+            ButtonEvent::DoubleClick => leds[LedId::Ld3].toggle(),
+            ButtonEvent::LongPress(_) => leds[LedId::Ld4].toggle(),
+            _ => (),
+        }
+    }
+
+    #[task(binds = OC15, local=[button, producer])]
+    fn button_task(cx: button_task::Context) {
+        // Dropped events are counted by the queue
+        cx.local
+            .producer
+            .push_edge(cx.local.button, get_ms_ticks())
+            .ok();
+    }
+
+    #[task(binds = OC0)]
+    fn ms_tick(_cx: ms_tick::Context) {
+        default_ms_irq_handler();
+    }
+}
@@ -0,0 +1,77 @@
+//! Low power example for the REB1 board
+//!
+//! The core sleeps until the button is pressed. All peripheral clocks except the ones required
+//! for the button interrupt and TIM1 are disabled while sleeping. TIM1 runs as a free running
+//! down counter to measure how long the board slept.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use va108xx_hal::{
+    gpio::{InterruptEdge, PinsA},
+    pac::{self, interrupt},
+    prelude::*,
+    timer::IrqCfg,
+};
+use vorago_reb1::{
+    button::Button,
+    leds::{LedId, Leds},
+    sleep::Sleep,
+};
+
+const SYS_CLK_HZ: u32 = 50_000_000;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    rprintln!("-- Vorago Button Wakeup Example --");
+    let mut dp = pac::Peripherals::take().unwrap();
+    let pinsa = PinsA::new(&mut dp.SYSCONFIG, Some(dp.IOCONFIG), dp.PORTA);
+    let button = Button::new(pinsa.pa11.into_floating_input())
+        .edge_irq(
+            InterruptEdge::HighToLow,
+            IrqCfg::new(pac::interrupt::OC15, true, true),
+            Some(&mut dp.SYSCONFIG),
+            Some(&mut dp.IRQSEL),
+        )
+        .debounced(4000.us(), SYS_CLK_HZ.hz(), &mut dp.SYSCONFIG)
+        .expect("configuring the button debounce filter failed");
+    let mut leds = Leds::new(
+        pinsa.pa10.into_push_pull_output(),
+        pinsa.pa7.into_push_pull_output(),
+        pinsa.pa6.into_push_pull_output(),
+    );
+    for led in leds.iter_mut() {
+        led.off();
+    }
+
+    // TIM1 counts down from the maximum value with the system clock. It wraps after roughly
+    // 85 seconds at 50 MHz.
+    dp.SYSCONFIG
+        .tim_clk_enable
+        .modify(|r, w| unsafe { w.bits(r.bits() | (1 << 1)) });
+    let tim1 = dp.TIM1;
+    tim1.rst_value.write(|w| unsafe { w.bits(u32::MAX) });
+    tim1.cnt_value.write(|w| unsafe { w.bits(u32::MAX) });
+    tim1.ctrl.write(|w| w.enable().set_bit());
+
+    let sleep = Sleep::new().keep_timer_clock::<pac::TIM1>();
+    loop {
+        rprintln!("Going to sleep, press the button to wake up");
+        let start = tim1.cnt_value.read().bits();
+        sleep
+            .sleep_until_button(&mut dp.SYSCONFIG, &button)
+            .expect("button can not wake up the core");
+        let slept_cycles = start.wrapping_sub(tim1.cnt_value.read().bits());
+        rprintln!(
+            "Woke up after {} ms",
+            slept_cycles as u64 * 1000 / SYS_CLK_HZ as u64
+        );
+        leds[LedId::Ld2].toggle();
+    }
+}
+
+#[interrupt]
+fn OC15() {}
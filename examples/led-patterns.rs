@@ -0,0 +1,72 @@
+//! Non-blocking LED pattern example
+//!
+//! The patterns are advanced from the main loop using the millisecond counter which is
+//! incremented by the OC0 timer interrupt, so the CPU is free to do other work in between.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_halt as _;
+use va108xx_hal::{
+    gpio::pins::PinsA,
+    pac::{self, interrupt},
+    prelude::*,
+    timer::{default_ms_irq_handler, get_ms_ticks, set_up_ms_timer, IrqCfg},
+};
+use vorago_reb1::{
+    led_patterns::{LedPatterns, Pattern, Repeat},
+    leds::Leds,
+};
+
+const SOS: [u32; 18] = [
+    150, 150, 150, 150, 150, 450, 450, 150, 450, 150, 450, 450, 150, 150, 150, 150, 150, 1500,
+];
+
+#[entry]
+fn main() -> ! {
+    let mut dp = pac::Peripherals::take().unwrap();
+    let pinsa = PinsA::new(&mut dp.SYSCONFIG, Some(dp.IOCONFIG), dp.PORTA);
+    set_up_ms_timer(
+        IrqCfg::new(pac::Interrupt::OC0, true, true),
+        &mut dp.SYSCONFIG,
+        Some(&mut dp.IRQSEL),
+        50.mhz(),
+        dp.TIM0,
+    );
+    let mut leds = Leds::new(
+        pinsa.pa10.into_push_pull_output(),
+        pinsa.pa7.into_push_pull_output(),
+        pinsa.pa6.into_push_pull_output(),
+    );
+    let mut patterns: LedPatterns = LedPatterns::new();
+    patterns.set_pattern(0, Pattern::Heartbeat { period_ms: 1000 });
+    patterns.set_pattern(
+        1,
+        Pattern::Blink {
+            period_ms: 200,
+            duty_percent: 20,
+            repeat: Repeat::Forever,
+        },
+    );
+    patterns.set_pattern(
+        2,
+        Pattern::Sequence {
+            durations_ms: &SOS,
+            repeat: Repeat::Times(3),
+        },
+    );
+    let mut last_ms = get_ms_ticks();
+    loop {
+        let now = get_ms_ticks();
+        patterns.tick(now.wrapping_sub(last_ms));
+        last_ms = now;
+        patterns.apply(&mut leds);
+        // Other work could be done here
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn OC0() {
+    default_ms_irq_handler();
+}
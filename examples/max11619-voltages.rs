@@ -0,0 +1,81 @@
+//! MAX11619 voltage measurement example
+//!
+//! Reads all four analog inputs of the REB1 board in millivolts. The potentiometer channel uses
+//! a gain and offset calibration which can be determined with two reference voltages.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use embedded_hal::spi;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use va108xx_hal::{
+    gpio::PinsA,
+    pac,
+    prelude::*,
+    spi::{Spi, SpiConfig, TransferConfig},
+    timer::set_up_ms_delay_provider,
+};
+use vorago_reb1::max11619::{
+    max11619_externally_clocked_no_wakeup, Calibration, Max11619Voltages, POTENTIOMETER_CHANNEL,
+};
+
+/// Voltage at the REF input of the ADC. Adapt this if a different reference is used.
+const EXTERNAL_REF_MV: u16 = 3300;
+/// Gain of 0.99 and an offset of 11 mV in the Q16.16 format
+const POTENTIOMETER_CALIBRATION: Calibration = Calibration::new(64880, 11);
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    rprintln!("-- Vorago ADC Voltages Example --");
+    let mut dp = pac::Peripherals::take().unwrap();
+    let mut delay = set_up_ms_delay_provider(&mut dp.SYSCONFIG, 50.mhz(), dp.TIM0);
+    let pinsa = PinsA::new(&mut dp.SYSCONFIG, None, dp.PORTA);
+    let (sck, mosi, miso) = (
+        pinsa.pa20.into_funsel_2(),
+        pinsa.pa19.into_funsel_2(),
+        pinsa.pa18.into_funsel_2(),
+    );
+    // Set the accelerometer chip select low in case the board slot is populated
+    let mut accel_cs = pinsa.pa16.into_push_pull_output();
+    accel_cs
+        .set_high()
+        .expect("Setting accelerometer chip select high failed");
+
+    let transfer_cfg = TransferConfig::new(
+        3.mhz(),
+        spi::MODE_0,
+        Some(pinsa.pa17.into_funsel_2()),
+        true,
+        false,
+    );
+    let spi = Spi::spib(
+        dp.SPIB,
+        (sck, miso, mosi),
+        50.mhz(),
+        SpiConfig::default(),
+        Some(&mut dp.SYSCONFIG),
+        Some(&transfer_cfg.downgrade()),
+    )
+    .downgrade();
+    // The externally clocked configuration uses the external reference
+    let adc = max11619_externally_clocked_no_wakeup(spi)
+        .expect("Creating externally clocked MAX11619 device failed");
+    let mut adc = Max11619Voltages::new(adc, EXTERNAL_REF_MV);
+    adc.set_calibration(POTENTIOMETER_CHANNEL, POTENTIOMETER_CALIBRATION)
+        .expect("Setting the potentiometer calibration failed");
+    loop {
+        let voltages = adc
+            .read_all_voltages()
+            .expect("Reading the ADC channels failed");
+        rprintln!(
+            "AN0: {}, AN1: {}, AN2: {}, Potentiometer: {}",
+            voltages[0],
+            voltages[1],
+            voltages[2],
+            voltages[3]
+        );
+        delay.delay_ms(500_u16);
+    }
+}
@@ -0,0 +1,80 @@
+//! Potentiometer example for the REB1 board
+//!
+//! Prints the knob position whenever it changes and shows it as a bar graph on the three LEDs.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use embedded_hal::spi;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use va108xx_hal::{
+    gpio::PinsA,
+    pac,
+    prelude::*,
+    spi::{Spi, SpiConfig, TransferConfig},
+    timer::set_up_ms_delay_provider,
+};
+use vorago_reb1::{
+    leds::Leds,
+    max11619::max11619_externally_clocked_no_wakeup,
+    potentiometer::{PotConfig, Potentiometer, FULL_SCALE_PERMILLE},
+};
+
+const SAMPLE_PERIOD_MS: u16 = 20;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    rprintln!("-- Vorago Potentiometer Example --");
+    let mut dp = pac::Peripherals::take().unwrap();
+    let mut delay = set_up_ms_delay_provider(&mut dp.SYSCONFIG, 50.mhz(), dp.TIM0);
+    let pinsa = PinsA::new(&mut dp.SYSCONFIG, None, dp.PORTA);
+    let (sck, mosi, miso) = (
+        pinsa.pa20.into_funsel_2(),
+        pinsa.pa19.into_funsel_2(),
+        pinsa.pa18.into_funsel_2(),
+    );
+    // Set the accelerometer chip select low in case the board slot is populated
+    let mut accel_cs = pinsa.pa16.into_push_pull_output();
+    accel_cs
+        .set_high()
+        .expect("Setting accelerometer chip select high failed");
+
+    let transfer_cfg = TransferConfig::new(
+        3.mhz(),
+        spi::MODE_0,
+        Some(pinsa.pa17.into_funsel_2()),
+        true,
+        false,
+    );
+    let spi = Spi::spib(
+        dp.SPIB,
+        (sck, miso, mosi),
+        50.mhz(),
+        SpiConfig::default(),
+        Some(&mut dp.SYSCONFIG),
+        Some(&transfer_cfg.downgrade()),
+    )
+    .downgrade();
+    let mut adc = max11619_externally_clocked_no_wakeup(spi)
+        .expect("Creating externally clocked MAX11619 device failed");
+    let mut leds = Leds::new(
+        pinsa.pa10.into_push_pull_output(),
+        pinsa.pa7.into_push_pull_output(),
+        pinsa.pa6.into_push_pull_output(),
+    );
+    let mut pot = Potentiometer::new(PotConfig::default());
+    loop {
+        let moved = pot
+            .update(&mut adc)
+            .expect("Reading the potentiometer failed");
+        if moved {
+            rprintln!("Potentiometer position: {} ‰", pot.position());
+            // Zero to three LEDs
+            let lit = pot.position() as u32 * 4 / (FULL_SCALE_PERMILLE as u32 + 1);
+            leds.set_mask((1 << lit) - 1);
+        }
+        delay.delay_ms(SAMPLE_PERIOD_MS);
+    }
+}
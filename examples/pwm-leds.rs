@@ -0,0 +1,36 @@
+//! LED brightness example using the PWM mode of the REB1 LEDs
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_halt as _;
+use va108xx_hal::{gpio::pins::PinsA, pac, prelude::*, timer::set_up_ms_delay_provider};
+use vorago_reb1::leds::PwmLeds;
+
+#[entry]
+fn main() -> ! {
+    let mut dp = pac::Peripherals::take().unwrap();
+    let pinsa = PinsA::new(&mut dp.SYSCONFIG, Some(dp.IOCONFIG), dp.PORTA);
+    let mut delay = set_up_ms_delay_provider(&mut dp.SYSCONFIG, 50.mhz(), dp.TIM0);
+    let mut leds = PwmLeds::new(
+        pinsa.pa10.into_push_pull_output(),
+        pinsa.pa7.into_push_pull_output(),
+        pinsa.pa6.into_push_pull_output(),
+        dp.TIM10,
+        dp.TIM7,
+        dp.TIM6,
+        50.mhz(),
+        &mut dp.SYSCONFIG,
+        1.khz(),
+    );
+    // Fixed brightness levels for LD3 and LD4
+    leds[1].set_brightness(10);
+    leds[2].set_brightness(60);
+    // Let LD2 fade in and out
+    loop {
+        for percent in (0..=100).chain((0..100).rev()) {
+            leds[0].set_brightness(percent);
+            delay.delay_ms(10_u16);
+        }
+    }
+}
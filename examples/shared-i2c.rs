@@ -0,0 +1,61 @@
+//! Shared I2C bus example for the REB1 board
+//!
+//! The on-board ADT75 and a second device share I2CA. The second device is accessed with raw
+//! transfers to show that any driver using the embedded-hal I2C traits can use a bus proxy.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use embedded_hal::blocking::i2c::WriteRead;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use va108xx_hal::{
+    i2c::{I2cMaster, I2cSpeed, MasterConfig},
+    pac,
+    prelude::*,
+    timer::set_up_ms_delay_provider,
+};
+use vorago_reb1::{
+    shared_i2c::CsI2cBus,
+    temp_sensor::{AddressPins, Adt75TempSensor},
+};
+
+/// Address of an EEPROM on a carrier board
+const EEPROM_ADDR: u8 = 0b1010000;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    rprintln!("-- Vorago Shared I2C Bus Example --");
+    let mut dp = pac::Peripherals::take().unwrap();
+    let mut delay = set_up_ms_delay_provider(&mut dp.SYSCONFIG, 50.mhz(), dp.TIM0);
+    unsafe {
+        cortex_m::peripheral::NVIC::unmask(pac::Interrupt::OC0);
+    }
+
+    let i2ca = I2cMaster::i2ca(
+        dp.I2CA,
+        MasterConfig::default(),
+        50.mhz(),
+        I2cSpeed::Regular100khz,
+        Some(&mut dp.SYSCONFIG),
+    );
+    // The bus lives until the end of main, which never returns
+    let bus = CsI2cBus::new(i2ca);
+
+    let mut temp_sensor = Adt75TempSensor::new(bus.acquire_i2c(), AddressPins::default())
+        .expect("Creating temperature sensor struct failed");
+    let mut eeprom = bus.acquire_i2c();
+    loop {
+        let temp = temp_sensor
+            .read_temperature()
+            .expect("Failed reading temperature");
+        rprintln!("Temperature in Celcius: {}", temp);
+        let mut first_byte: [u8; 1] = [0; 1];
+        match eeprom.write_read(EEPROM_ADDR, &[0, 0], &mut first_byte) {
+            Ok(()) => rprintln!("EEPROM byte 0: {:#04x}", first_byte[0]),
+            Err(e) => rprintln!("EEPROM read failed: {:?}", e),
+        }
+        delay.delay_ms(500_u16);
+    }
+}
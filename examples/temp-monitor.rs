@@ -0,0 +1,70 @@
+//! Temperature monitor example for the REB1 board
+//!
+//! Samples the on-board ADT75 every 500 ms and prints the statistics and alarm transitions. The
+//! sensor is read through a recoverable I2C bus which retries failed transfers.
+#![no_main]
+#![no_std]
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use va108xx_hal::{pac, prelude::*, timer::set_up_ms_delay_provider};
+use vorago_reb1::{
+    i2c::RetryPolicy,
+    temp_monitor::{AlarmConfig, TempMonitor},
+    temp_sensor::{Adt75TempSensor, Temperature},
+};
+
+const SAMPLE_PERIOD_MS: u32 = 500;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    rprintln!("-- Vorago Temperature Monitor Example --");
+    let mut dp = pac::Peripherals::take().unwrap();
+    let mut delay = set_up_ms_delay_provider(&mut dp.SYSCONFIG, 50.mhz(), dp.TIM0);
+    unsafe {
+        cortex_m::peripheral::NVIC::unmask(pac::Interrupt::OC0);
+    }
+
+    let policy = RetryPolicy {
+        retry_on_nack: true,
+        ..Default::default()
+    };
+    let syscfg = Some(&mut dp.SYSCONFIG);
+    let mut temp_sensor =
+        Adt75TempSensor::new_on_board_recoverable(dp.I2CA, 50.mhz(), syscfg, policy)
+            .expect("Creating temperature sensor struct failed");
+    let mut monitor: TempMonitor = TempMonitor::new(AlarmConfig {
+        high: Some(Temperature::from_celsius(35)),
+        low: Some(Temperature::from_celsius(10)),
+        // 0.5 °C per second
+        slope_limit_mc_per_s: Some(500),
+        slope_hysteresis_mc_per_s: 100,
+        ..Default::default()
+    });
+    let mut now_ms: u32 = 0;
+    loop {
+        match monitor.sample(&mut temp_sensor, now_ms) {
+            Ok(events) => {
+                for event in events {
+                    rprintln!("Alarm event: {:?}", event);
+                }
+            }
+            Err(e) => rprintln!("Reading the temperature failed: {:?}", e),
+        }
+        if let (Some(latest), Some(min), Some(max)) =
+            (monitor.latest(), monitor.min(), monitor.max())
+        {
+            rprintln!(
+                "Temperature: {} (min {}, max {}), average {} m°C, rate {:?} m°C/s",
+                latest.temp,
+                min,
+                max,
+                monitor.average_millicelsius().unwrap_or(0),
+                monitor.rate_mc_per_s()
+            );
+        }
+        delay.delay_ms(SAMPLE_PERIOD_MS);
+        now_ms = now_ms.wrapping_add(SAMPLE_PERIOD_MS);
+    }
+}
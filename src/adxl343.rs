@@ -0,0 +1,168 @@
+//! # Driver for the Analog Devices ADXL343 accelerometer
+//!
+//! [Datasheet](https://www.analog.com/media/en/technical-documentation/data-sheets/adxl343.pdf)
+//!
+//! The default REB1 board is not populated with the ADXL343BCCZ-RL7. The sensor is connected to
+//! SPIB with the chip select on PA16 and uses SPI mode 3. The driver always uses the full
+//! resolution mode, where the scale factor is 256 LSB/g independently of the measurement range.
+//!
+//! ## Examples
+//!
+//! - [ADXL343 accelerometer example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/adxl343-accelerometer.rs)
+use embedded_hal::blocking::spi::{Transfer, Write};
+
+use crate::sensors::{Acceleration, Accelerometer, MilliG};
+
+const READ_MASK: u8 = 1 << 7;
+const MULTI_BYTE_MASK: u8 = 1 << 6;
+/// Fixed value of the device ID register
+pub const DEVICE_ID: u8 = 0xE5;
+const PWR_MEASUREMENT_MODE_MASK: u8 = 1 << 3;
+const DATA_FORMAT_FULL_RES_MASK: u8 = 1 << 3;
+/// Scale factor in the full resolution mode
+const LSB_PER_G: i32 = 256;
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum RegAddresses {
+    DeviceId = 0x00,
+    BwRate = 0x2C,
+    PowerCtl = 0x2D,
+    DataFormat = 0x31,
+    DataX0 = 0x32,
+}
+
+/// Measurement range
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Range {
+    G2 = 0b00,
+    G4 = 0b01,
+    G8 = 0b10,
+    G16 = 0b11,
+}
+
+/// Output data rate of the BW_RATE register. The bandwidth is half the data rate.
+#[derive(PartialEq, Eq, Debug, Default, Copy, Clone)]
+pub enum DataRate {
+    Hz0_10 = 0b0000,
+    Hz0_20 = 0b0001,
+    Hz0_39 = 0b0010,
+    Hz0_78 = 0b0011,
+    Hz1_56 = 0b0100,
+    Hz3_13 = 0b0101,
+    Hz6_25 = 0b0110,
+    Hz12_5 = 0b0111,
+    Hz25 = 0b1000,
+    Hz50 = 0b1001,
+    /// Reset value
+    #[default]
+    Hz100 = 0b1010,
+    Hz200 = 0b1011,
+    Hz400 = 0b1100,
+    Hz800 = 0b1101,
+    Hz1600 = 0b1110,
+    Hz3200 = 0b1111,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    Spi(E),
+    /// The device ID register did not contain [DEVICE_ID]
+    InvalidDeviceId(u8),
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(e: E) -> Self {
+        Error::Spi(e)
+    }
+}
+
+pub struct Adxl343<SPI> {
+    spi: SPI,
+}
+
+impl<SPI, E> Adxl343<SPI>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+{
+    /// Create the driver and check the device ID. The sensor is configured for the full
+    /// resolution mode with a range of ±2 g and switched to measurement mode.
+    pub fn new(spi: SPI) -> Result<Self, Error<E>> {
+        let mut adxl = Adxl343 { spi };
+        let id = adxl.device_id()?;
+        if id != DEVICE_ID {
+            return Err(Error::InvalidDeviceId(id));
+        }
+        adxl.set_range(Range::G2)?;
+        adxl.set_measurement_mode(true)?;
+        Ok(adxl)
+    }
+
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+
+    fn read_reg(&mut self, reg: RegAddresses) -> Result<u8, E> {
+        let mut buf: [u8; 2] = [READ_MASK | reg as u8, 0];
+        let reply = self.spi.transfer(&mut buf)?;
+        Ok(reply[1])
+    }
+
+    fn write_reg(&mut self, reg: RegAddresses, value: u8) -> Result<(), E> {
+        self.spi.write(&[reg as u8, value])
+    }
+
+    pub fn device_id(&mut self) -> Result<u8, E> {
+        self.read_reg(RegAddresses::DeviceId)
+    }
+
+    pub fn set_range(&mut self, range: Range) -> Result<(), E> {
+        self.write_reg(
+            RegAddresses::DataFormat,
+            DATA_FORMAT_FULL_RES_MASK | range as u8,
+        )
+    }
+
+    /// The sensor is in standby mode after power-up and only measures in measurement mode
+    pub fn set_measurement_mode(&mut self, enable: bool) -> Result<(), E> {
+        let power_ctl = if enable { PWR_MEASUREMENT_MODE_MASK } else { 0 };
+        self.write_reg(RegAddresses::PowerCtl, power_ctl)
+    }
+
+    /// Set the output data rate. The sensor uses [DataRate::Hz100] after power-up.
+    pub fn set_data_rate(&mut self, rate: DataRate) -> Result<(), E> {
+        self.write_reg(RegAddresses::BwRate, rate as u8)
+    }
+
+    /// Read the raw values of the X, Y and Z axis with a single multi-byte transfer
+    pub fn read_raw(&mut self) -> Result<[i16; 3], E> {
+        let mut buf: [u8; 7] = [0; 7];
+        buf[0] = READ_MASK | MULTI_BYTE_MASK | RegAddresses::DataX0 as u8;
+        let reply = self.spi.transfer(&mut buf)?;
+        Ok([
+            i16::from_le_bytes([reply[1], reply[2]]),
+            i16::from_le_bytes([reply[3], reply[4]]),
+            i16::from_le_bytes([reply[5], reply[6]]),
+        ])
+    }
+}
+
+/// Convert a raw value in the full resolution mode to milli-g
+fn raw_to_milli_g(raw: i16) -> MilliG {
+    MilliG(raw as i32 * 1000 / LSB_PER_G)
+}
+
+impl<SPI, E> Accelerometer for Adxl343<SPI>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+{
+    type Error = E;
+
+    fn read_acceleration(&mut self) -> Result<Acceleration, E> {
+        let [x, y, z] = self.read_raw()?;
+        Ok(Acceleration {
+            x: raw_to_milli_g(x),
+            y: raw_to_milli_g(y),
+            z: raw_to_milli_g(z),
+        })
+    }
+}
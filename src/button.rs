@@ -1,23 +1,87 @@
 //! # API for the REB1 button
 //!
+//! The button can be polled or used with interrupts. For async code, the button edge interrupt
+//! configured with [Button::edge_irq] has to call [Button::on_interrupt]. The futures returned
+//! by [Button::wait_for_press], [Button::wait_for_release] and [Button::wait_for_edge] are
+//! then woken by the interrupt.
+//!
+//! Only a single waker is stored, so only one task may wait for the button at a time. If a
+//! second task starts waiting, it replaces the waker of the first task, which is then not woken
+//! by the next edge anymore.
+//!
 //! ## Examples
 //!
 //! - [Button Blinky with IRQs](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/blinky-button-irq.rs)
 //! - [Button Blinky with IRQs and RTIC](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/blinky-button-rtic.rs)
+//! - [Button wakeup from sleep](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/button-wakeup.rs)
+//! - [Async button](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/button-async.rs)
+use core::{
+    cell::RefCell,
+    future::poll_fn,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use cortex_m::interrupt::Mutex;
 use va108xx_hal::{
+    clock::set_clk_div_register,
     gpio::{FilterClkSel, FilterType, InputFloating, InterruptEdge, InterruptLevel, Pin, PA11},
     pac,
     prelude::*,
+    time::{Hertz, MicroSeconds},
     utility::IrqCfg,
 };
 
+/// Number of filter clock cycles used by [Button::debounced]
+const DEBOUNCE_FILTER_CYCLES: u64 = 4;
+const BUTTON_PIN: u32 = 11;
+
+/// Task waiting for the next button edge. Only one waiting task is supported.
+static WAKER: Mutex<RefCell<Option<Waker>>> = Mutex::new(RefCell::new(None));
+/// Number of press and release edges seen by [Button::on_interrupt]. Only written by the
+/// interrupt handler.
+static PRESS_EDGES: AtomicU32 = AtomicU32::new(0);
+static RELEASE_EDGES: AtomicU32 = AtomicU32::new(0);
+/// Number of edges in bits 31 to 1 and the button state at the last edge in bit 0, so both can
+/// be read consistently with a single load. Only written by the interrupt handler.
+static LAST_EDGE: AtomicU32 = AtomicU32::new(0);
+const LAST_EDGE_PRESSED: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DebounceError {
+    /// The debounce time is 0 or can not be reached with the maximum filter clock divider
+    InvalidDebounceTime,
+    /// All filter clocks are already in use with dividers which do not match the requested
+    /// debounce time
+    FilterClockConflict,
+}
+
+/// Hardware filter settings selected by [Button::debounced]
+#[derive(Debug, PartialEq, Eq)]
+pub struct DebounceSettings {
+    pub clk_sel: FilterClkSel,
+    /// Divider of the system clock written to the IOCONFIG_CLKDIV register
+    pub divider: u32,
+    /// Number of filter clock cycles the input needs to be stable
+    pub filter_cycles: u8,
+    /// Achieved debounce window in microseconds. This is always at least the requested debounce
+    /// time.
+    pub window_us: u32,
+}
+
 pub struct Button {
     button: Pin<PA11, InputFloating>,
+    debounce: Option<DebounceSettings>,
+    irq: Option<pac::Interrupt>,
 }
 
 impl Button {
     pub fn new(pin: Pin<PA11, InputFloating>) -> Button {
-        Button { button: pin }
+        Button {
+            button: pin,
+            debounce: None,
+            irq: None,
+        }
     }
 
     pub fn pressed(&self) -> bool {
@@ -39,6 +103,7 @@ impl Button {
         self.button = self
             .button
             .interrupt_edge(edge_type, irq_cfg, syscfg, irqsel);
+        self.irq = Some(irq_cfg.irq);
         self
     }
 
@@ -51,6 +116,7 @@ impl Button {
         irqsel: Option<&mut pac::IRQSEL>,
     ) -> Self {
         self.button = self.button.interrupt_level(level, irq_cfg, syscfg, irqsel);
+        self.irq = Some(irq_cfg.irq);
         self
     }
 
@@ -62,4 +128,187 @@ impl Button {
         self.button = self.button.filter_type(filter, clksel);
         self
     }
+
+    /// Configures the hardware filter to debounce the button for at least the given time.
+    ///
+    /// The filter always requires the input to be stable for four filter clock cycles. A filter
+    /// clock which is already configured with the required divider is re-used. Otherwise, the
+    /// first unused filter clock is configured. A filter clock is only considered unused if its
+    /// divider register is 0 and no other pin has a filter selected which is clocked by it, so
+    /// the filters of other pins are never changed. The selected settings and the achieved
+    /// debounce window can be retrieved with [Self::debounce_settings].
+    pub fn debounced(
+        mut self,
+        debounce_time: impl Into<MicroSeconds>,
+        sys_clk: impl Into<Hertz>,
+        syscfg: &mut pac::SYSCONFIG,
+    ) -> Result<Self, DebounceError> {
+        let sys_clk = sys_clk.into().0 as u64;
+        let cycles = debounce_time.into().0 as u64 * sys_clk / 1_000_000;
+        if cycles == 0 {
+            return Err(DebounceError::InvalidDebounceTime);
+        }
+        let divider = cycles.div_ceil(DEBOUNCE_FILTER_CYCLES);
+        if divider > u32::MAX as u64 {
+            return Err(DebounceError::InvalidDebounceTime);
+        }
+        let divider = divider as u32;
+        let dividers = [
+            syscfg.ioconfig_clkdiv1.read().bits(),
+            syscfg.ioconfig_clkdiv2.read().bits(),
+            syscfg.ioconfig_clkdiv3.read().bits(),
+            syscfg.ioconfig_clkdiv4.read().bits(),
+            syscfg.ioconfig_clkdiv5.read().bits(),
+            syscfg.ioconfig_clkdiv6.read().bits(),
+            syscfg.ioconfig_clkdiv7.read().bits(),
+        ];
+        let matching = dividers.iter().position(|div| *div == divider);
+        let free = dividers
+            .iter()
+            .enumerate()
+            .position(|(idx, div)| *div == 0 && !filter_clk_in_use(filter_clk_sel(idx)));
+        let clk_idx = match matching.or(free) {
+            Some(idx) => idx,
+            None => return Err(DebounceError::FilterClockConflict),
+        };
+        if matching.is_none() {
+            set_clk_div_register(syscfg, filter_clk_sel(clk_idx), divider);
+        }
+        self.button = self
+            .button
+            .filter_type(FilterType::FilterFourClockCycles, filter_clk_sel(clk_idx));
+        self.debounce = Some(DebounceSettings {
+            clk_sel: filter_clk_sel(clk_idx),
+            divider,
+            filter_cycles: DEBOUNCE_FILTER_CYCLES as u8,
+            window_us: (DEBOUNCE_FILTER_CYCLES * divider as u64 * 1_000_000).div_ceil(sys_clk)
+                as u32,
+        });
+        Ok(self)
+    }
+
+    /// Must be called from the interrupt handler of the button edge interrupt to wake up the task
+    /// waiting for the button. The button state is sampled in the handler, so short presses are
+    /// not lost even if the waiting task is polled after the button was released again.
+    pub fn on_interrupt() {
+        // Safety: Read-only access to the input register of port A. The raw register is used so
+        // the result does not depend on the DATAMASK setting of the pin.
+        let port = unsafe { &*pac::PORTA::ptr() };
+        let pressed = port.datainraw().read().bits() & (1 << BUTTON_PIN) == 0;
+        let counter = if pressed {
+            &PRESS_EDGES
+        } else {
+            &RELEASE_EDGES
+        };
+        counter.store(
+            counter.load(Ordering::Relaxed).wrapping_add(1),
+            Ordering::Release,
+        );
+        let edges = (LAST_EDGE.load(Ordering::Relaxed) >> 1).wrapping_add(1);
+        LAST_EDGE.store(edges << 1 | pressed as u32, Ordering::Release);
+        cortex_m::interrupt::free(|cs| {
+            if let Some(waker) = WAKER.borrow(cs).borrow_mut().take() {
+                waker.wake();
+            }
+        });
+    }
+
+    /// Wait until the button is pressed. Completes immediately if the button is already
+    /// pressed. The button needs to be configured for falling or both edges.
+    pub async fn wait_for_press(&self) {
+        let start = PRESS_EDGES.load(Ordering::Acquire);
+        poll_fn(|cx| {
+            register_waker(cx);
+            if self.pressed() || PRESS_EDGES.load(Ordering::Acquire) != start {
+                return Poll::Ready(());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Wait until the button is released. Completes immediately if the button is already
+    /// released. The button needs to be configured for rising or both edges.
+    pub async fn wait_for_release(&self) {
+        let start = RELEASE_EDGES.load(Ordering::Acquire);
+        poll_fn(|cx| {
+            register_waker(cx);
+            if self.released() || RELEASE_EDGES.load(Ordering::Acquire) != start {
+                return Poll::Ready(());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Wait for the next edge configured with [Self::edge_irq]. Returns [true] if the button
+    /// was pressed at the time of the edge. If several edges occurred before the task was
+    /// polled, the state at the last edge is returned.
+    pub async fn wait_for_edge(&self) -> bool {
+        let start = LAST_EDGE.load(Ordering::Acquire) >> 1;
+        poll_fn(|cx| {
+            register_waker(cx);
+            let last_edge = LAST_EDGE.load(Ordering::Acquire);
+            if last_edge >> 1 != start {
+                return Poll::Ready(last_edge & LAST_EDGE_PRESSED != 0);
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Interrupt vector of the button if it was configured with [Self::edge_irq] or
+    /// [Self::level_irq]
+    pub fn irq(&self) -> Option<pac::Interrupt> {
+        self.irq
+    }
+
+    /// Hardware filter settings if the button was configured with [Self::debounced]
+    pub fn debounce_settings(&self) -> Option<&DebounceSettings> {
+        self.debounce.as_ref()
+    }
+}
+
+/// Register the waker before the condition is checked so no edge is missed in between
+fn register_waker(cx: &Context<'_>) {
+    cortex_m::interrupt::free(|cs| {
+        let mut waker = WAKER.borrow(cs).borrow_mut();
+        match waker.as_ref() {
+            Some(current) if current.will_wake(cx.waker()) => (),
+            _ => *waker = Some(cx.waker().clone()),
+        }
+    });
+}
+
+/// Returns [true] if a pin other than the button pin uses a filter which is clocked by the given
+/// filter clock. The divider of such a clock must not be changed, even if it is still 0.
+fn filter_clk_in_use(clk_sel: FilterClkSel) -> bool {
+    let clk_sel = clk_sel as u8;
+    // Safety: Read-only access to the pin configuration registers
+    let ioconfig = unsafe { &*pac::IOCONFIG::ptr() };
+    let port_a = ioconfig.porta.iter().enumerate().filter_map(|(num, cfg)| {
+        let cfg = cfg.read();
+        (num != BUTTON_PIN as usize).then(|| (cfg.flttype().bits(), cfg.fltclk().bits()))
+    });
+    let port_b = ioconfig.portb.iter().map(|cfg| {
+        let cfg = cfg.read();
+        (cfg.flttype().bits(), cfg.fltclk().bits())
+    });
+    // Only the filter types from one clock cycle upwards use the filter clock
+    port_a.chain(port_b).any(|(flt_type, flt_clk)| {
+        flt_type >= FilterType::FilterOneClockCycle as u8 && flt_clk == clk_sel
+    })
+}
+
+/// Filter clock select for the clock divider registers 1 to 7
+fn filter_clk_sel(idx: usize) -> FilterClkSel {
+    match idx {
+        0 => FilterClkSel::Clk1,
+        1 => FilterClkSel::Clk2,
+        2 => FilterClkSel::Clk3,
+        3 => FilterClkSel::Clk4,
+        4 => FilterClkSel::Clk5,
+        5 => FilterClkSel::Clk6,
+        _ => FilterClkSel::Clk7,
+    }
 }
@@ -0,0 +1,381 @@
+//! # Software debouncer and gesture detection for the REB1 button
+//!
+//! The [Debouncer] is a pure state machine which is fed with button samples and millisecond
+//! timestamps. It can be driven by periodically sampling the [Button], for example from the
+//! millisecond timer interrupt, or by the button edge interrupt configured with
+//! [Button::edge_irq]. When only edges are used, [Debouncer::poll] needs to be called
+//! periodically as well so that time based events like [ButtonEvent::ShortClick] are generated.
+//!
+//! The state machine does not access any hardware, so it can be tested on the host with
+//! synthetic timestamps.
+use crate::{button::Button, events::Events};
+
+/// Events generated by the [Debouncer]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ButtonEvent {
+    /// Debounced transition to the pressed state
+    Pressed,
+    /// Debounced transition to the released state
+    Released,
+    /// Short press which was not followed by a second press within the double click window
+    ShortClick,
+    /// Press which was held for at least the long press time. Generated while the button is still
+    /// held, as soon as the debouncer is updated or polled after the long press time elapsed.
+    /// Contains the press duration in milliseconds at that point. No [ButtonEvent::ShortClick]
+    /// or [ButtonEvent::DoubleClick] is generated for a long press.
+    LongPress(u32),
+    /// Two short presses within the double click window
+    DoubleClick,
+}
+
+/// Timing configuration for the [Debouncer]. All times are in milliseconds.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct DebounceConfig {
+    /// The raw button state needs to be stable for this time to be accepted
+    pub debounce_ms: u32,
+    /// Minimum press duration for a [ButtonEvent::LongPress]
+    pub long_press_ms: u32,
+    /// Maximum time between the release of the first click and the press of the second click
+    /// for a [ButtonEvent::DoubleClick]. A value of 0 disables double click detection and
+    /// reports [ButtonEvent::ShortClick] immediately on release.
+    pub double_click_ms: u32,
+}
+
+impl Default for DebounceConfig {
+    fn default() -> Self {
+        DebounceConfig {
+            debounce_ms: 20,
+            long_press_ms: 1000,
+            double_click_ms: 300,
+        }
+    }
+}
+
+/// Events generated by a single call to the [Debouncer]
+pub type ButtonEvents = Events<ButtonEvent, 3>;
+
+/// Debouncer for a single button.
+///
+/// The debouncer needs to be fed with the raw button state with [Debouncer::update] or
+/// [Debouncer::sample] and polled with [Debouncer::poll] when no new sample is available. All
+/// calls take a free-running millisecond timestamp which may wrap around.
+pub struct Debouncer {
+    cfg: DebounceConfig,
+    raw: bool,
+    raw_since: u32,
+    stable: bool,
+    press_start: u32,
+    /// Set once the long press event was generated for the current press
+    long_press_reported: bool,
+    /// Release time of a short click which might still become a double click
+    pending_click: Option<u32>,
+    /// Set if the current press is the second press of a double click
+    second_press: bool,
+}
+
+impl Debouncer {
+    /// Create a new debouncer. The button is assumed to be released initially.
+    pub fn new(cfg: DebounceConfig) -> Self {
+        Debouncer {
+            cfg,
+            raw: false,
+            raw_since: 0,
+            stable: false,
+            press_start: 0,
+            long_press_reported: false,
+            pending_click: None,
+            second_press: false,
+        }
+    }
+
+    pub fn config(&self) -> &DebounceConfig {
+        &self.cfg
+    }
+
+    /// Debounced button state. [true] means that the button is pressed.
+    pub fn is_pressed(&self) -> bool {
+        self.stable
+    }
+
+    /// Sample the REB1 button
+    pub fn sample(&mut self, button: &Button, now_ms: u32) -> ButtonEvents {
+        self.update(button.pressed(), now_ms)
+    }
+
+    /// Feed a raw button state. This can be a periodic sample or the state read inside the edge
+    /// interrupt handler.
+    pub fn update(&mut self, pressed: bool, now_ms: u32) -> ButtonEvents {
+        if pressed != self.raw {
+            self.raw = pressed;
+            self.raw_since = now_ms;
+        }
+        self.poll(now_ms)
+    }
+
+    /// Advance the time based parts of the state machine without a new sample.
+    pub fn poll(&mut self, now_ms: u32) -> ButtonEvents {
+        let mut events = ButtonEvents::default();
+        if let Some(release_time) = self.pending_click {
+            // A pending click expires unless a second press is already being debounced
+            if !self.raw
+                && !self.second_press
+                && now_ms.wrapping_sub(release_time) > self.cfg.double_click_ms
+            {
+                self.pending_click = None;
+                events.push(ButtonEvent::ShortClick);
+            }
+        }
+        if self.raw != self.stable && now_ms.wrapping_sub(self.raw_since) >= self.cfg.debounce_ms {
+            self.stable = self.raw;
+            // Use the time of the raw transition so the debounce time does not distort the
+            // measured durations
+            let transition = self.raw_since;
+            if self.stable {
+                self.on_press(transition, &mut events);
+            } else {
+                self.on_release(transition, &mut events);
+            }
+        }
+        if self.stable && !self.long_press_reported {
+            let duration = now_ms.wrapping_sub(self.press_start);
+            if duration >= self.cfg.long_press_ms {
+                self.on_long_press(duration, &mut events);
+            }
+        }
+        events
+    }
+
+    fn on_press(&mut self, time: u32, events: &mut ButtonEvents) {
+        events.push(ButtonEvent::Pressed);
+        self.press_start = time;
+        self.long_press_reported = false;
+        self.second_press = match self.pending_click {
+            Some(release_time) => time.wrapping_sub(release_time) <= self.cfg.double_click_ms,
+            None => false,
+        };
+        if !self.second_press && self.pending_click.take().is_some() {
+            events.push(ButtonEvent::ShortClick);
+        }
+    }
+
+    fn on_release(&mut self, time: u32, events: &mut ButtonEvents) {
+        events.push(ButtonEvent::Released);
+        if self.long_press_reported {
+            // Already handled while the button was held
+        } else if self.second_press {
+            self.pending_click = None;
+            events.push(ButtonEvent::DoubleClick);
+        } else if self.cfg.double_click_ms == 0 {
+            events.push(ButtonEvent::ShortClick);
+        } else {
+            self.pending_click = Some(time);
+        }
+        self.second_press = false;
+    }
+
+    fn on_long_press(&mut self, duration: u32, events: &mut ButtonEvents) {
+        self.long_press_reported = true;
+        // A long second press cancels the double click
+        if self.pending_click.take().is_some() {
+            events.push(ButtonEvent::ShortClick);
+        }
+        self.second_press = false;
+        events.push(ButtonEvent::LongPress(duration));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(events: ButtonEvents) -> [Option<ButtonEvent>; 3] {
+        let mut collected = [None; 3];
+        for (slot, event) in collected.iter_mut().zip(events) {
+            *slot = Some(event);
+        }
+        collected
+    }
+
+    /// Drive the debouncer with 1 ms samples from `start` to `end` and collect all events
+    fn run(
+        debouncer: &mut Debouncer,
+        pressed: bool,
+        start: u32,
+        end: u32,
+        out: &mut [Option<ButtonEvent>; 8],
+        len: &mut usize,
+    ) {
+        let mut now = start;
+        while now != end {
+            for event in debouncer.update(pressed, now) {
+                out[*len] = Some(event);
+                *len += 1;
+            }
+            now = now.wrapping_add(1);
+        }
+    }
+
+    fn events_of(steps: &[(bool, u32)], start: u32) -> ([Option<ButtonEvent>; 8], usize) {
+        let mut debouncer = Debouncer::new(DebounceConfig::default());
+        let mut out = [None; 8];
+        let mut len = 0;
+        let mut now = start;
+        for (pressed, duration) in steps {
+            let end = now.wrapping_add(*duration);
+            run(&mut debouncer, *pressed, now, end, &mut out, &mut len);
+            now = end;
+        }
+        (out, len)
+    }
+
+    #[test]
+    fn bounces_are_rejected() {
+        let mut debouncer = Debouncer::new(DebounceConfig::default());
+        for now in 0..10 {
+            assert!(debouncer.update(now % 2 == 0, now).is_empty());
+        }
+        assert!(!debouncer.is_pressed());
+        assert!(debouncer.update(true, 10).is_empty());
+        assert!(debouncer.poll(29).is_empty());
+        assert_eq!(
+            collect(debouncer.poll(30)),
+            [Some(ButtonEvent::Pressed), None, None]
+        );
+        assert!(debouncer.is_pressed());
+    }
+
+    #[test]
+    fn short_click_after_double_click_window() {
+        let (events, len) = events_of(&[(true, 100), (false, 400)], 0);
+        assert_eq!(
+            events[..len],
+            [
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::Released),
+                Some(ButtonEvent::ShortClick)
+            ]
+        );
+    }
+
+    #[test]
+    fn short_click_timing() {
+        let mut debouncer = Debouncer::new(DebounceConfig::default());
+        debouncer.update(true, 0);
+        debouncer.poll(20);
+        debouncer.update(false, 100);
+        assert_eq!(
+            collect(debouncer.poll(120)),
+            [Some(ButtonEvent::Released), None, None]
+        );
+        // The double click window starts at the raw release time
+        assert!(debouncer.poll(400).is_empty());
+        assert_eq!(
+            collect(debouncer.poll(401)),
+            [Some(ButtonEvent::ShortClick), None, None]
+        );
+    }
+
+    #[test]
+    fn double_click() {
+        let (events, len) = events_of(&[(true, 80), (false, 100), (true, 80), (false, 400)], 0);
+        assert_eq!(
+            events[..len],
+            [
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::Released),
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::Released),
+                Some(ButtonEvent::DoubleClick)
+            ]
+        );
+    }
+
+    #[test]
+    fn long_press_is_reported_while_held() {
+        let mut debouncer = Debouncer::new(DebounceConfig::default());
+        debouncer.update(true, 0);
+        assert_eq!(
+            collect(debouncer.poll(20)),
+            [Some(ButtonEvent::Pressed), None, None]
+        );
+        assert!(debouncer.poll(999).is_empty());
+        assert_eq!(
+            collect(debouncer.poll(1000)),
+            [Some(ButtonEvent::LongPress(1000)), None, None]
+        );
+        assert!(debouncer.poll(2000).is_empty());
+        debouncer.update(false, 3000);
+        assert_eq!(
+            collect(debouncer.poll(3020)),
+            [Some(ButtonEvent::Released), None, None]
+        );
+        assert!(debouncer.poll(5000).is_empty());
+    }
+
+    #[test]
+    fn long_press_is_reported_before_late_release() {
+        let mut debouncer = Debouncer::new(DebounceConfig::default());
+        debouncer.update(true, 0);
+        assert_eq!(
+            collect(debouncer.poll(20)),
+            [Some(ButtonEvent::Pressed), None, None]
+        );
+        // No poll while the button is held, the raw release is the first update
+        assert_eq!(
+            collect(debouncer.update(false, 1500)),
+            [Some(ButtonEvent::LongPress(1500)), None, None]
+        );
+        assert_eq!(
+            collect(debouncer.poll(1520)),
+            [Some(ButtonEvent::Released), None, None]
+        );
+    }
+
+    #[test]
+    fn long_second_press_cancels_double_click() {
+        let (events, len) = events_of(&[(true, 80), (false, 100), (true, 1100), (false, 400)], 0);
+        assert_eq!(
+            events[..len],
+            [
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::Released),
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::ShortClick),
+                Some(ButtonEvent::LongPress(1000)),
+                Some(ButtonEvent::Released)
+            ]
+        );
+    }
+
+    #[test]
+    fn double_click_disabled() {
+        let mut debouncer = Debouncer::new(DebounceConfig {
+            double_click_ms: 0,
+            ..Default::default()
+        });
+        debouncer.update(true, 0);
+        debouncer.poll(20);
+        debouncer.update(false, 50);
+        assert_eq!(
+            collect(debouncer.poll(70)),
+            [
+                Some(ButtonEvent::Released),
+                Some(ButtonEvent::ShortClick),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamp_wraparound() {
+        let (events, len) = events_of(&[(true, 1200), (false, 100)], u32::MAX - 500);
+        assert_eq!(
+            events[..len],
+            [
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::LongPress(1000)),
+                Some(ButtonEvent::Released)
+            ]
+        );
+    }
+}
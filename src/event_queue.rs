@@ -0,0 +1,307 @@
+//! # Lock-free queue to pass button events from an interrupt to the main loop
+//!
+//! The [EventQueue] is a single-producer single-consumer ring buffer which only requires atomic
+//! loads and stores, so it works on the Cortex-M0 without a critical section. Typically, the
+//! button edge interrupt pushes timestamped [EdgeEvent]s with the [Producer] and the application
+//! drains them with the [Consumer] outside of interrupt context. Events which do not fit into
+//! the queue are dropped and counted, so missed presses are visible.
+//!
+//! The queue is split into its two halves with [EventQueue::split]. A queue with a `'static`
+//! lifetime can be created with the RTIC `local` resources or the `cortex_m::singleton` macro.
+//!
+//! ## Examples
+//!
+//! - [Button events with RTIC](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/button-events-rtic.rs)
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use crate::button::Button;
+
+/// Timestamped button edge
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct EdgeEvent {
+    /// Timestamp in milliseconds, for example from [`va108xx_hal::timer::get_ms_ticks`]
+    pub timestamp_ms: u32,
+    /// Button state after the edge
+    pub pressed: bool,
+}
+
+impl EdgeEvent {
+    pub fn new(timestamp_ms: u32, pressed: bool) -> Self {
+        EdgeEvent {
+            timestamp_ms,
+            pressed,
+        }
+    }
+}
+
+/// Single-producer single-consumer queue with a capacity of `N` elements
+pub struct EventQueue<T = EdgeEvent, const N: usize = 16> {
+    buf: UnsafeCell<[MaybeUninit<T>; N]>,
+    /// Write index, only written by the producer. The indices run from 0 to 2N - 1, so a full
+    /// queue can be distinguished from an empty one for any N.
+    head: AtomicUsize,
+    /// Read index, only written by the consumer
+    tail: AtomicUsize,
+    /// Number of dropped elements, only written by the producer
+    overflows: AtomicU32,
+}
+
+// Safety: The producer and the consumer never access the same slot at the same time
+unsafe impl<T: Send, const N: usize> Sync for EventQueue<T, N> {}
+
+impl<T, const N: usize> Default for EventQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> EventQueue<T, N> {
+    const INIT: MaybeUninit<T> = MaybeUninit::uninit();
+
+    pub const fn new() -> Self {
+        EventQueue {
+            buf: UnsafeCell::new([Self::INIT; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overflows: AtomicU32::new(0),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Split the queue into its producer and consumer half
+    pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+        let queue: &Self = self;
+        (Producer { queue }, Consumer { queue })
+    }
+
+    fn len(&self) -> usize {
+        Self::distance(
+            self.head.load(Ordering::Acquire),
+            self.tail.load(Ordering::Acquire),
+        )
+    }
+
+    /// Number of elements between the read and the write index
+    fn distance(head: usize, tail: usize) -> usize {
+        if head >= tail {
+            head - tail
+        } else {
+            head + 2 * N - tail
+        }
+    }
+
+    /// Advance a read or write index, wrapping around at 2N
+    fn next_idx(idx: usize) -> usize {
+        if idx + 1 == 2 * N {
+            0
+        } else {
+            idx + 1
+        }
+    }
+
+    fn slot(&self, idx: usize) -> *mut MaybeUninit<T> {
+        let idx = if idx >= N { idx - N } else { idx };
+        // Safety: The index is always smaller than N
+        unsafe { (self.buf.get() as *mut MaybeUninit<T>).add(idx) }
+    }
+}
+
+impl<T, const N: usize> Drop for EventQueue<T, N> {
+    fn drop(&mut self) {
+        let (_, mut consumer) = self.split();
+        while consumer.pop().is_some() {}
+    }
+}
+
+/// Producer half of the [EventQueue], usually owned by the interrupt handler
+pub struct Producer<'a, T, const N: usize> {
+    queue: &'a EventQueue<T, N>,
+}
+
+impl<T, const N: usize> Producer<'_, T, N> {
+    /// Push an element. If the queue is full, the element is returned and the overflow counter
+    /// is incremented.
+    pub fn push(&mut self, elem: T) -> Result<(), T> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+        if EventQueue::<T, N>::distance(head, self.queue.tail.load(Ordering::Acquire)) >= N {
+            let overflows = self.queue.overflows.load(Ordering::Relaxed);
+            self.queue
+                .overflows
+                .store(overflows.saturating_add(1), Ordering::Release);
+            return Err(elem);
+        }
+        // Safety: The slot is not accessed by the consumer until head is incremented
+        unsafe { (*self.queue.slot(head)).write(elem) };
+        self.queue
+            .head
+            .store(EventQueue::<T, N>::next_idx(head), Ordering::Release);
+        Ok(())
+    }
+
+    /// Number of elements which were dropped because the queue was full
+    pub fn overflows(&self) -> u32 {
+        self.queue.overflows.load(Ordering::Acquire)
+    }
+}
+
+impl<const N: usize> Producer<'_, EdgeEvent, N> {
+    /// Read the current button state and push it with the given timestamp. This is intended
+    /// to be called from the button edge interrupt handler.
+    pub fn push_edge(&mut self, button: &Button, timestamp_ms: u32) -> Result<(), EdgeEvent> {
+        self.push(EdgeEvent::new(timestamp_ms, button.pressed()))
+    }
+}
+
+/// Consumer half of the [EventQueue], usually owned by the main loop
+pub struct Consumer<'a, T, const N: usize> {
+    queue: &'a EventQueue<T, N>,
+}
+
+impl<'a, T, const N: usize> Consumer<'a, T, N> {
+    /// Pop the oldest element
+    pub fn pop(&mut self) -> Option<T> {
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+        if self.queue.head.load(Ordering::Acquire) == tail {
+            return None;
+        }
+        // Safety: The slot was initialized by the producer before head was incremented
+        let elem = unsafe { (*self.queue.slot(tail)).assume_init_read() };
+        self.queue
+            .tail
+            .store(EventQueue::<T, N>::next_idx(tail), Ordering::Release);
+        Some(elem)
+    }
+
+    /// Iterator which pops elements until the queue is empty
+    pub fn drain(&mut self) -> Drain<'_, 'a, T, N> {
+        Drain { consumer: self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of elements which were dropped because the queue was full
+    pub fn overflows(&self) -> u32 {
+        self.queue.overflows.load(Ordering::Acquire)
+    }
+}
+
+/// Iterator returned by [Consumer::drain]
+pub struct Drain<'c, 'a, T, const N: usize> {
+    consumer: &'c mut Consumer<'a, T, N>,
+}
+
+impl<T, const N: usize> Iterator for Drain<'_, '_, T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.consumer.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_and_full() {
+        let mut queue: EventQueue<u32, 3> = EventQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        assert!(consumer.is_empty());
+        assert_eq!(consumer.pop(), None);
+        for value in 0..3 {
+            assert_eq!(producer.push(value), Ok(()));
+        }
+        assert_eq!(consumer.len(), 3);
+        assert_eq!(producer.push(3), Err(3));
+        assert_eq!(producer.overflows(), 1);
+        assert_eq!(consumer.overflows(), 1);
+        assert_eq!(consumer.pop(), Some(0));
+        assert_eq!(producer.push(4), Ok(()));
+        assert_eq!(consumer.len(), 3);
+    }
+
+    #[test]
+    fn ordering() {
+        let mut queue: EventQueue<EdgeEvent, 4> = EventQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        for timestamp in 0..4 {
+            producer
+                .push(EdgeEvent::new(timestamp, timestamp % 2 == 0))
+                .unwrap();
+        }
+        let mut expected = 0;
+        for event in consumer.drain() {
+            assert_eq!(event, EdgeEvent::new(expected, expected % 2 == 0));
+            expected += 1;
+        }
+        assert_eq!(expected, 4);
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn wraparound_with_non_power_of_two_capacity() {
+        let mut queue: EventQueue<u32, 3> = EventQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        let mut next_push = 0;
+        let mut next_pop = 0;
+        // Run the indices around the 2N range many times with different fill levels
+        for round in 0..100 {
+            for _ in 0..(round % 3) + 1 {
+                if producer.push(next_push).is_ok() {
+                    next_push += 1;
+                }
+            }
+            assert!(consumer.len() <= 3);
+            for _ in 0..(round % 2) + 1 {
+                if let Some(value) = consumer.pop() {
+                    assert_eq!(value, next_pop);
+                    next_pop += 1;
+                }
+            }
+        }
+        while let Some(value) = consumer.pop() {
+            assert_eq!(value, next_pop);
+            next_pop += 1;
+        }
+        assert_eq!(next_pop, next_push);
+        assert!(next_push > 6);
+    }
+
+    #[test]
+    fn remaining_elements_are_dropped() {
+        use core::cell::Cell;
+
+        struct Counted<'a>(&'a Cell<u32>);
+
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        {
+            let mut queue: EventQueue<Counted, 4> = EventQueue::new();
+            let (mut producer, mut consumer) = queue.split();
+            for _ in 0..3 {
+                assert!(producer.push(Counted(&drops)).is_ok());
+            }
+            drop(consumer.pop());
+            assert_eq!(drops.get(), 1);
+        }
+        assert_eq!(drops.get(), 3);
+    }
+}
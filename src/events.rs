@@ -0,0 +1,102 @@
+//! # Fixed-capacity event lists
+//!
+//! The BSP state machines like the [Debouncer](crate::debouncer::Debouncer) and the
+//! [TempMonitor](crate::temp_monitor::TempMonitor) can generate several events in a single call.
+//! They return the events in an [Events] list with a fixed capacity, so no allocator is
+//! required.
+
+/// Fixed-capacity list of events which is consumed by iterating over it. The events are yielded
+/// in the order in which they were generated.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Events<T, const N: usize> {
+    events: [Option<T>; N],
+    len: usize,
+    idx: usize,
+}
+
+impl<T: Copy, const N: usize> Events<T, N> {
+    pub const fn new() -> Self {
+        Events {
+            events: [None; N],
+            len: 0,
+            idx: 0,
+        }
+    }
+
+    /// Add an event. The capacity is chosen by the state machines so that it can not be
+    /// exceeded, so an overflow panics.
+    pub(crate) fn push(&mut self, event: T) {
+        self.events[self.len] = Some(event);
+        self.len += 1;
+    }
+
+    /// No events left
+    pub fn is_empty(&self) -> bool {
+        self.idx == self.len
+    }
+}
+
+impl<T: Copy, const N: usize> Default for Events<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy, const N: usize> Iterator for Events<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.idx == self.len {
+            return None;
+        }
+        self.idx += 1;
+        self.events[self.idx - 1]
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.idx;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: Copy, const N: usize> ExactSizeIterator for Events<T, N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yields_events_in_order() {
+        let mut events = Events::<u8, 3>::new();
+        assert!(events.is_empty());
+        events.push(1);
+        events.push(2);
+        assert!(!events.is_empty());
+        assert_eq!(events.len(), 2);
+        assert_eq!(events.next(), Some(1));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.next(), Some(2));
+        assert!(events.is_empty());
+        assert_eq!(events.next(), None);
+    }
+
+    #[test]
+    fn full_capacity() {
+        let mut events = Events::<u8, 2>::default();
+        events.push(1);
+        events.push(2);
+        let mut out = [0; 2];
+        for (slot, event) in out.iter_mut().zip(events) {
+            *slot = event;
+        }
+        assert_eq!(out, [1, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn overflow_panics() {
+        let mut events = Events::<u8, 1>::new();
+        events.push(1);
+        events.push(2);
+    }
+}
@@ -0,0 +1,541 @@
+//! # I2C error handling and bus recovery
+//!
+//! [I2cError] is the error type of [RecoverableI2c]. It distinguishes the failure causes which
+//! require different reactions, independently of the I2C implementation. Bus errors are
+//! classified with the [I2cErrorKind] trait, which is implemented for the HAL error type.
+//!
+//! The BSP drivers like the [ADT75 driver](crate::temp_sensor::Adt75TempSensor) are generic over
+//! the embedded-hal I2C traits and return the error type of the bus unchanged. When they are used
+//! with a [RecoverableI2c], their errors are therefore classified [I2cError]s.
+//!
+//! A device which is reset in the middle of a read transfer can keep SDA low while it waits for
+//! the remaining clock pulses, which locks up the bus. [RecoverableI2c] wraps the HAL
+//! [I2cMaster] and detects a stuck bus before each transfer. [RecoverableI2c::recover_bus]
+//! cancels the pending transfer and re-initializes the I2C master. The [RetryPolicy] specifies
+//! which errors trigger a recovery and how often a transfer is retried.
+//!
+//! ## Bus pins
+//!
+//! The SCL and SDA lines of I2CA and I2CB use dedicated pads of the VA108xx which are not part
+//! of PORTA or PORTB. This is also why the HAL I2C drivers do not take any pins. The I2C pads can
+//! not be used as GPIOs, so the nine SCL pulses which free a device holding SDA low can not be
+//! generated on them. On the REB1 board, a recovery of the on-board bus with the ADT75 therefore
+//! only resets the I2C master, see
+//! [Adt75TempSensor::new_on_board_recoverable](crate::temp_sensor::Adt75TempSensor::new_on_board_recoverable).
+//!
+//! Boards which additionally connect the bus lines to two GPIO pins can pass these pins with
+//! [RecoverableI2c::with_recovery_pins]. The recovery then also clocks out nine SCL pulses on
+//! them and generates a STOP condition before the I2C master is re-initialized.
+use embedded_hal::{
+    blocking::i2c::{Read, SevenBitAddress, Write, WriteRead},
+    digital::v2::OutputPin,
+};
+use va108xx_hal::{
+    gpio::DynPin,
+    i2c::{Error as HalError, I2cMaster, I2cSpeed, MasterConfig},
+    pac::{self, i2ca, I2CA, I2CB, SYSCONFIG},
+    time::Hertz,
+};
+
+/// Number of SCL pulses required to free a device which holds SDA low
+const RECOVERY_CLOCK_PULSES: u8 = 9;
+/// Clock frequency used for the recovery pulses
+const RECOVERY_CLOCK_HZ: u32 = 100_000;
+/// Command register value which cancels the current transfer
+const I2C_CMD_CANCEL: u32 = 0b100;
+
+/// Classification of I2C errors
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ErrorKind {
+    /// The address was not acknowledged, for example because no device with this address is
+    /// connected or the device is busy
+    NackAddr,
+    /// A data byte was not acknowledged
+    NackData,
+    /// Another master or a stuck device drove SDA low
+    ArbitrationLost,
+    /// The bus did not become idle in time
+    Timeout,
+    Other,
+}
+
+/// Trait to classify the errors of an I2C implementation
+pub trait I2cErrorKind {
+    fn kind(&self) -> ErrorKind;
+}
+
+impl I2cErrorKind for HalError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            HalError::NackAddr => ErrorKind::NackAddr,
+            HalError::NackData => ErrorKind::NackData,
+            HalError::ArbitrationLost => ErrorKind::ArbitrationLost,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Error type of the BSP I2C drivers
+#[derive(Debug, PartialEq, Eq)]
+pub enum I2cError<E> {
+    NackAddr,
+    NackData,
+    ArbitrationLost,
+    Timeout,
+    /// Other error of the underlying I2C implementation
+    Other(E),
+}
+
+impl<E> I2cError<E> {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            I2cError::NackAddr => ErrorKind::NackAddr,
+            I2cError::NackData => ErrorKind::NackData,
+            I2cError::ArbitrationLost => ErrorKind::ArbitrationLost,
+            I2cError::Timeout => ErrorKind::Timeout,
+            I2cError::Other(_) => ErrorKind::Other,
+        }
+    }
+}
+
+impl<E> I2cErrorKind for I2cError<E> {
+    fn kind(&self) -> ErrorKind {
+        I2cError::kind(self)
+    }
+}
+
+impl<E: I2cErrorKind> From<E> for I2cError<E> {
+    fn from(e: E) -> Self {
+        match e.kind() {
+            ErrorKind::NackAddr => I2cError::NackAddr,
+            ErrorKind::NackData => I2cError::NackData,
+            ErrorKind::ArbitrationLost => I2cError::ArbitrationLost,
+            ErrorKind::Timeout => I2cError::Timeout,
+            ErrorKind::Other => I2cError::Other(e),
+        }
+    }
+}
+
+/// Specifies how [RecoverableI2c] reacts to failed transfers
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub max_retries: u8,
+    /// Retry transfers which failed with a NACK, for example while an EEPROM is busy writing
+    pub retry_on_nack: bool,
+    /// Recover the bus before retrying after an arbitration loss or a timeout
+    pub recover_bus: bool,
+    /// Number of system clock cycles the bus lines may stay low before a transfer is started
+    pub idle_timeout_cycles: u32,
+}
+
+/// Reaction of [RecoverableI2c] to a failed transfer, see [RetryPolicy::action]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RetryAction {
+    /// Return the error
+    Fail,
+    /// Retry the transfer
+    Retry,
+    /// Recover the bus with [RecoverableI2c::recover_bus] and retry the transfer
+    RecoverAndRetry,
+}
+
+impl RetryPolicy {
+    /// Reaction to a failed transfer with the given error kind after the given number of
+    /// retries
+    pub fn action(&self, kind: ErrorKind, retries: u8) -> RetryAction {
+        if retries >= self.max_retries {
+            return RetryAction::Fail;
+        }
+        match kind {
+            ErrorKind::ArbitrationLost | ErrorKind::Timeout if self.recover_bus => {
+                RetryAction::RecoverAndRetry
+            }
+            ErrorKind::ArbitrationLost | ErrorKind::Timeout => RetryAction::Retry,
+            ErrorKind::NackAddr | ErrorKind::NackData if self.retry_on_nack => RetryAction::Retry,
+            _ => RetryAction::Fail,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 2,
+            retry_on_nack: false,
+            recover_bus: true,
+            // 10 ms at 50 MHz
+            idle_timeout_cycles: 500_000,
+        }
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::I2CA {}
+    impl Sealed for super::I2CB {}
+}
+
+/// I2C peripherals which can be used with [RecoverableI2c]. This trait is sealed and only
+/// implemented for [I2CA] and [I2CB].
+pub trait I2cInstance: private::Sealed + Sized {
+    /// Register block of the peripheral
+    ///
+    /// # Safety
+    ///
+    /// The caller must own the peripheral or the I2C master which uses it, and accesses must not
+    /// interfere with a transfer of that master.
+    unsafe fn reg() -> &'static i2ca::RegisterBlock;
+
+    /// Re-create the master after the previous instance was dropped
+    ///
+    /// # Safety
+    ///
+    /// The peripheral is stolen, so no other instance of the peripheral or of an I2C master using
+    /// it may exist.
+    unsafe fn reinit(sys_clk: Hertz, speed: I2cSpeed) -> I2cMaster<Self, SevenBitAddress>;
+}
+
+impl I2cInstance for I2CA {
+    unsafe fn reg() -> &'static i2ca::RegisterBlock {
+        &*I2CA::ptr()
+    }
+
+    unsafe fn reinit(sys_clk: Hertz, speed: I2cSpeed) -> I2cMaster<Self, SevenBitAddress> {
+        let i2ca = pac::Peripherals::steal().I2CA;
+        I2cMaster::i2ca(i2ca, MasterConfig::default(), sys_clk, speed, None)
+    }
+}
+
+impl I2cInstance for I2CB {
+    unsafe fn reg() -> &'static i2ca::RegisterBlock {
+        &*I2CB::ptr()
+    }
+
+    unsafe fn reinit(sys_clk: Hertz, speed: I2cSpeed) -> I2cMaster<Self, SevenBitAddress> {
+        let i2cb = pac::Peripherals::steal().I2CB;
+        I2cMaster::i2cb(i2cb, MasterConfig::default(), sys_clk, speed, None)
+    }
+}
+
+/// I2C master which detects a stuck bus and recovers it according to the [RetryPolicy]. See
+/// the [module documentation](self) for the bus pins.
+pub struct RecoverableI2c<I2C: I2cInstance> {
+    i2c: Option<I2cMaster<I2C, SevenBitAddress>>,
+    sys_clk: Hertz,
+    speed: I2cSpeed,
+    /// GPIO pins connected to SCL and SDA, in this order
+    recovery_pins: Option<(DynPin, DynPin)>,
+    policy: RetryPolicy,
+    recoveries: u32,
+}
+
+impl RecoverableI2c<I2CA> {
+    /// Create a recoverable I2C master on I2CA, which is the bus of the on-board ADT75
+    pub fn i2ca(
+        i2ca: I2CA,
+        sys_clk: impl Into<Hertz> + Copy,
+        speed: I2cSpeed,
+        sys_cfg: Option<&mut SYSCONFIG>,
+    ) -> Self {
+        let i2c = I2cMaster::i2ca(i2ca, MasterConfig::default(), sys_clk, speed, sys_cfg);
+        Self::new(i2c, sys_clk.into(), speed)
+    }
+}
+
+impl RecoverableI2c<I2CB> {
+    /// Create a recoverable I2C master on I2CB
+    pub fn i2cb(
+        i2cb: I2CB,
+        sys_clk: impl Into<Hertz> + Copy,
+        speed: I2cSpeed,
+        sys_cfg: Option<&mut SYSCONFIG>,
+    ) -> Self {
+        let i2c = I2cMaster::i2cb(i2cb, MasterConfig::default(), sys_clk, speed, sys_cfg);
+        Self::new(i2c, sys_clk.into(), speed)
+    }
+}
+
+impl<I2C: I2cInstance> RecoverableI2c<I2C>
+where
+    I2cMaster<I2C, SevenBitAddress>: Write<SevenBitAddress, Error = HalError>
+        + Read<SevenBitAddress, Error = HalError>
+        + WriteRead<SevenBitAddress, Error = HalError>,
+{
+    fn new(i2c: I2cMaster<I2C, SevenBitAddress>, sys_clk: Hertz, speed: I2cSpeed) -> Self {
+        RecoverableI2c {
+            i2c: Some(i2c),
+            sys_clk,
+            speed,
+            recovery_pins: None,
+            policy: RetryPolicy::default(),
+            recoveries: 0,
+        }
+    }
+
+    /// Use two GPIO pins which are connected to the SCL and SDA lines of the bus to clock out a
+    /// stuck device during [Self::recover_bus]. The pins are passed in their normal operating
+    /// mode, which is usually an input. They are only switched to open-drain outputs during the
+    /// recovery and are restored to their previous mode afterwards. The HAL can not check the
+    /// wiring, so the caller is responsible for passing the correct pins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if SCL and SDA are the same pin.
+    pub fn with_recovery_pins(mut self, scl: impl Into<DynPin>, sda: impl Into<DynPin>) -> Self {
+        let (scl, sda) = (scl.into(), sda.into());
+        assert!(scl.id() != sda.id(), "SCL and SDA must be different pins");
+        self.recovery_pins = Some((scl, sda));
+        self
+    }
+
+    /// Release the recovery pins
+    pub fn take_recovery_pins(&mut self) -> Option<(DynPin, DynPin)> {
+        self.recovery_pins.take()
+    }
+
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Number of bus recoveries performed so far
+    pub fn recoveries(&self) -> u32 {
+        self.recoveries
+    }
+
+    /// Wait until both bus lines are released
+    fn wait_for_idle_bus(&self) -> Result<(), I2cError<HalError>> {
+        // Safety: Read-only access to the status register of the owned peripheral
+        let reg = unsafe { I2C::reg() };
+        // Each iteration takes at least a few cycles, so this is a conservative limit
+        for _ in 0..self.policy.idle_timeout_cycles / 4 {
+            let status = reg.status.read();
+            if status.raw_scl().bit_is_set() && status.raw_sda().bit_is_set() {
+                return Ok(());
+            }
+        }
+        Err(I2cError::Timeout)
+    }
+
+    /// Free a stuck bus. A pending transfer is cancelled and the I2C master is re-initialized
+    /// with the default master configuration. With [recovery pins](Self::with_recovery_pins),
+    /// nine clock pulses are generated on SCL before, followed by a STOP condition. Returns
+    /// [I2cError::Timeout] if the bus lines are not released after the recovery.
+    pub fn recover_bus(&mut self) -> Result<(), I2cError<HalError>> {
+        self.recoveries = self.recoveries.wrapping_add(1);
+        if self.i2c.take().is_some() {
+            // Safety: The master was dropped and this is the only user of the peripheral
+            let reg = unsafe { I2C::reg() };
+            // Cancel a pending transfer and disable the master
+            reg.cmd.write(|w| unsafe { w.bits(I2C_CMD_CANCEL) });
+            reg.ctrl.modify(|_, w| w.enable().clear_bit());
+        }
+        if let Some((scl, sda)) = self.recovery_pins.as_mut() {
+            clock_out_bus(scl, sda, self.sys_clk);
+        }
+        // Safety: The previous master was dropped and the peripheral is only used by this
+        // instance
+        self.i2c = Some(unsafe { I2C::reinit(self.sys_clk, self.speed) });
+        self.wait_for_idle_bus()
+    }
+
+    /// Run a transfer and apply the retry policy
+    fn transfer(
+        &mut self,
+        mut f: impl FnMut(&mut I2cMaster<I2C, SevenBitAddress>) -> Result<(), HalError>,
+    ) -> Result<(), I2cError<HalError>> {
+        let mut retries = 0;
+        loop {
+            let result = self
+                .wait_for_idle_bus()
+                .and_then(|_| match self.i2c.as_mut() {
+                    Some(i2c) => f(i2c).map_err(I2cError::from),
+                    None => Err(I2cError::Timeout),
+                });
+            let err = match result {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            match self.policy.action(err.kind(), retries) {
+                RetryAction::Fail => return Err(err),
+                RetryAction::Retry => (),
+                RetryAction::RecoverAndRetry => {
+                    // A failed recovery is reported by the next attempt
+                    self.recover_bus().ok();
+                }
+            }
+            retries += 1;
+        }
+    }
+}
+
+/// Clock out a device which holds SDA low with nine SCL pulses and generate a STOP condition.
+/// The pins are restored to their previous mode afterwards.
+fn clock_out_bus(scl: &mut DynPin, sda: &mut DynPin, sys_clk: Hertz) {
+    let scl_mode = scl.mode();
+    let sda_mode = sda.mode();
+    sda.into_readable_open_drain_output();
+    scl.into_readable_open_drain_output();
+    let half_period = sys_clk.0 / RECOVERY_CLOCK_HZ / 2;
+    sda.set_high().ok();
+    for _ in 0..RECOVERY_CLOCK_PULSES {
+        scl.set_low().ok();
+        cortex_m::asm::delay(half_period);
+        scl.set_high().ok();
+        cortex_m::asm::delay(half_period);
+    }
+    // STOP condition: SDA goes high while SCL is high
+    scl.set_low().ok();
+    sda.set_low().ok();
+    cortex_m::asm::delay(half_period);
+    scl.set_high().ok();
+    cortex_m::asm::delay(half_period);
+    sda.set_high().ok();
+    cortex_m::asm::delay(half_period);
+    scl.into_mode(scl_mode);
+    sda.into_mode(sda_mode);
+}
+
+impl<I2C: I2cInstance> Write<SevenBitAddress> for RecoverableI2c<I2C>
+where
+    I2cMaster<I2C, SevenBitAddress>: Write<SevenBitAddress, Error = HalError>
+        + Read<SevenBitAddress, Error = HalError>
+        + WriteRead<SevenBitAddress, Error = HalError>,
+{
+    type Error = I2cError<HalError>;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.transfer(|i2c| i2c.write(address, bytes))
+    }
+}
+
+impl<I2C: I2cInstance> Read<SevenBitAddress> for RecoverableI2c<I2C>
+where
+    I2cMaster<I2C, SevenBitAddress>: Write<SevenBitAddress, Error = HalError>
+        + Read<SevenBitAddress, Error = HalError>
+        + WriteRead<SevenBitAddress, Error = HalError>,
+{
+    type Error = I2cError<HalError>;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.transfer(|i2c| i2c.read(address, buffer))
+    }
+}
+
+impl<I2C: I2cInstance> WriteRead<SevenBitAddress> for RecoverableI2c<I2C>
+where
+    I2cMaster<I2C, SevenBitAddress>: Write<SevenBitAddress, Error = HalError>
+        + Read<SevenBitAddress, Error = HalError>
+        + WriteRead<SevenBitAddress, Error = HalError>,
+{
+    type Error = I2cError<HalError>;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.transfer(|i2c| i2c.write_read(address, bytes, buffer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hal_error_classification() {
+        assert_eq!(HalError::NackAddr.kind(), ErrorKind::NackAddr);
+        assert_eq!(HalError::NackData.kind(), ErrorKind::NackData);
+        assert_eq!(HalError::ArbitrationLost.kind(), ErrorKind::ArbitrationLost);
+        assert_eq!(HalError::InsufficientDataReceived.kind(), ErrorKind::Other);
+        assert_eq!(HalError::DataTooLarge.kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn conversion_from_hal_error() {
+        assert_eq!(I2cError::from(HalError::NackAddr), I2cError::NackAddr);
+        assert_eq!(I2cError::from(HalError::NackData), I2cError::NackData);
+        assert_eq!(
+            I2cError::from(HalError::ArbitrationLost),
+            I2cError::ArbitrationLost
+        );
+        assert_eq!(
+            I2cError::from(HalError::WrongAddrMode),
+            I2cError::Other(HalError::WrongAddrMode)
+        );
+        assert_eq!(I2cError::<HalError>::Timeout.kind(), ErrorKind::Timeout);
+        assert_eq!(
+            I2cError::Other(HalError::DataTooLarge).kind(),
+            ErrorKind::Other
+        );
+    }
+
+    #[test]
+    fn default_policy() {
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            policy.action(ErrorKind::ArbitrationLost, 0),
+            RetryAction::RecoverAndRetry
+        );
+        assert_eq!(
+            policy.action(ErrorKind::Timeout, 1),
+            RetryAction::RecoverAndRetry
+        );
+        assert_eq!(policy.action(ErrorKind::Timeout, 2), RetryAction::Fail);
+        assert_eq!(policy.action(ErrorKind::NackAddr, 0), RetryAction::Fail);
+        assert_eq!(policy.action(ErrorKind::NackData, 0), RetryAction::Fail);
+        assert_eq!(policy.action(ErrorKind::Other, 0), RetryAction::Fail);
+    }
+
+    #[test]
+    fn retry_on_nack_without_recovery() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            retry_on_nack: true,
+            recover_bus: false,
+            ..Default::default()
+        };
+        for retries in 0..3 {
+            assert_eq!(
+                policy.action(ErrorKind::NackAddr, retries),
+                RetryAction::Retry
+            );
+            assert_eq!(
+                policy.action(ErrorKind::NackData, retries),
+                RetryAction::Retry
+            );
+            assert_eq!(
+                policy.action(ErrorKind::ArbitrationLost, retries),
+                RetryAction::Retry
+            );
+        }
+        assert_eq!(policy.action(ErrorKind::NackAddr, 3), RetryAction::Fail);
+        assert_eq!(policy.action(ErrorKind::Other, 0), RetryAction::Fail);
+    }
+
+    #[test]
+    fn no_retries() {
+        let policy = RetryPolicy {
+            max_retries: 0,
+            retry_on_nack: true,
+            ..Default::default()
+        };
+        for kind in [
+            ErrorKind::NackAddr,
+            ErrorKind::NackData,
+            ErrorKind::ArbitrationLost,
+            ErrorKind::Timeout,
+            ErrorKind::Other,
+        ] {
+            assert_eq!(policy.action(kind, 0), RetryAction::Fail);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! # Non-blocking LED pattern engine
+//!
+//! The [LedPatterns] engine holds one [Pattern] per LED and advances all of them with the
+//! [LedPatterns::tick] function. It does not block and does not access any hardware by itself,
+//! so it can be driven from a millisecond timer interrupt like the one set up with
+//! [`va108xx_hal::timer::set_up_ms_timer`], from a RTIC task or with synthetic ticks on the host.
+//! The resulting LED states can be written to the REB1 LEDs with [LedPatterns::apply].
+//!
+//! ## Examples
+//!
+//! - [LED pattern example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/led-patterns.rs)
+use crate::leds::Led;
+
+/// Length of a heartbeat pulse in milliseconds
+pub const HEARTBEAT_PULSE_MS: u32 = 100;
+
+/// Number of times a pattern is repeated
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Repeat {
+    Forever,
+    /// Repeat the pattern the given number of times. The LED is turned off afterwards.
+    Times(u32),
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Pattern {
+    /// LED is permanently off
+    Off,
+    /// LED is permanently on
+    On,
+    /// Regular blinking. The LED is on for the first `duty_percent` percent of each period.
+    Blink {
+        period_ms: u32,
+        duty_percent: u8,
+        repeat: Repeat,
+    },
+    /// Two short pulses of [HEARTBEAT_PULSE_MS] at the start of each period
+    Heartbeat { period_ms: u32 },
+    /// User defined sequence of durations in milliseconds. The LED is on for the first duration,
+    /// off for the second one, on for the third one and so on.
+    Sequence {
+        durations_ms: &'static [u32],
+        repeat: Repeat,
+    },
+}
+
+impl Pattern {
+    /// Duration of one pattern cycle in milliseconds. Returns [None] for static patterns.
+    pub fn cycle_ms(&self) -> Option<u32> {
+        match self {
+            Pattern::Off | Pattern::On => None,
+            Pattern::Blink { period_ms, .. } | Pattern::Heartbeat { period_ms } => Some(*period_ms),
+            Pattern::Sequence { durations_ms, .. } => Some(
+                durations_ms
+                    .iter()
+                    .fold(0u32, |sum, duration| sum.saturating_add(*duration)),
+            ),
+        }
+    }
+
+    const fn repeat(&self) -> Repeat {
+        match self {
+            Pattern::Blink { repeat, .. } | Pattern::Sequence { repeat, .. } => *repeat,
+            _ => Repeat::Forever,
+        }
+    }
+
+    /// LED state at the given time offset inside one pattern cycle
+    fn state_at(&self, offset_ms: u32) -> bool {
+        match self {
+            Pattern::Off => false,
+            Pattern::On => true,
+            Pattern::Blink {
+                period_ms,
+                duty_percent,
+                ..
+            } => {
+                // The product is widened to 64 bits because a u32 product would overflow for
+                // periods above about 42.9 s
+                (offset_ms as u64) < *period_ms as u64 * (*duty_percent).min(100) as u64 / 100
+            }
+            Pattern::Heartbeat { .. } => {
+                offset_ms < HEARTBEAT_PULSE_MS
+                    || (2 * HEARTBEAT_PULSE_MS..3 * HEARTBEAT_PULSE_MS).contains(&offset_ms)
+            }
+            Pattern::Sequence { durations_ms, .. } => {
+                let mut end: u32 = 0;
+                for (idx, duration) in durations_ms.iter().enumerate() {
+                    end = end.saturating_add(*duration);
+                    if offset_ms < end {
+                        return idx % 2 == 0;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+/// State of a single pattern slot
+#[derive(Debug, Copy, Clone)]
+struct PatternSlot {
+    pattern: Pattern,
+    offset_ms: u32,
+    cycles: u32,
+    finished: bool,
+}
+
+impl PatternSlot {
+    const fn new(pattern: Pattern) -> Self {
+        PatternSlot {
+            pattern,
+            offset_ms: 0,
+            cycles: 0,
+            // A pattern which is repeated zero times is finished before the first tick
+            finished: matches!(pattern.repeat(), Repeat::Times(0)),
+        }
+    }
+
+    fn advance(&mut self, elapsed_ms: u32) {
+        let cycle_ms = match self.pattern.cycle_ms() {
+            Some(cycle_ms) if cycle_ms > 0 && !self.finished => cycle_ms,
+            _ => return,
+        };
+        let total = self.offset_ms as u64 + elapsed_ms as u64;
+        let cycles = (total / cycle_ms as u64).min(u32::MAX as u64) as u32;
+        self.offset_ms = (total % cycle_ms as u64) as u32;
+        self.cycles = self.cycles.saturating_add(cycles);
+        if let Repeat::Times(times) = self.pattern.repeat() {
+            if self.cycles >= times {
+                self.finished = true;
+            }
+        }
+    }
+
+    fn is_on(&self) -> bool {
+        !self.finished && self.pattern.state_at(self.offset_ms)
+    }
+}
+
+/// Pattern engine for `N` LEDs. The default of 3 matches the number of LEDs on the REB1 board.
+pub struct LedPatterns<const N: usize = 3> {
+    slots: [PatternSlot; N],
+}
+
+impl<const N: usize> Default for LedPatterns<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> LedPatterns<N> {
+    /// Create a new pattern engine with all LEDs off
+    pub const fn new() -> Self {
+        LedPatterns {
+            slots: [PatternSlot::new(Pattern::Off); N],
+        }
+    }
+
+    /// Set the pattern for the LED with the given index. The pattern starts at the beginning of
+    /// its first cycle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is larger than or equal to `N`.
+    pub fn set_pattern(&mut self, idx: usize, pattern: Pattern) {
+        self.slots[idx] = PatternSlot::new(pattern);
+    }
+
+    /// Currently configured pattern for the LED with the given index.
+    pub fn pattern(&self, idx: usize) -> Pattern {
+        self.slots[idx].pattern
+    }
+
+    /// Returns [true] if a pattern with a finite repeat count has completed.
+    pub fn finished(&self, idx: usize) -> bool {
+        self.slots[idx].finished
+    }
+
+    /// Advance all patterns by the elapsed time in milliseconds. When driven from the
+    /// millisecond timer interrupt, this would be called with a value of 1.
+    pub fn tick(&mut self, elapsed_ms: u32) {
+        for slot in self.slots.iter_mut() {
+            slot.advance(elapsed_ms);
+        }
+    }
+
+    /// Desired state for the LED with the given index. [true] means that the LED should be on.
+    pub fn is_on(&self, idx: usize) -> bool {
+        self.slots[idx].is_on()
+    }
+
+    /// Desired state for all LEDs
+    pub fn states(&self) -> [bool; N] {
+        let mut states = [false; N];
+        for (state, slot) in states.iter_mut().zip(self.slots.iter()) {
+            *state = slot.is_on();
+        }
+        states
+    }
+
+    /// Write the current state of all patterns to the given LEDs.
+    pub fn apply(&self, leds: &mut [Led]) {
+        for (led, slot) in leds.iter_mut().zip(self.slots.iter()) {
+            if slot.is_on() {
+                led.on();
+            } else {
+                led.off();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Advance the engine in 1 ms steps and record the state of the first LED before each tick
+    fn trace(pattern: Pattern, ticks: usize) -> [bool; 64] {
+        let mut engine: LedPatterns<1> = LedPatterns::new();
+        engine.set_pattern(0, pattern);
+        let mut states = [false; 64];
+        for state in states.iter_mut().take(ticks) {
+            *state = engine.is_on(0);
+            engine.tick(1);
+        }
+        states
+    }
+
+    #[test]
+    fn static_patterns() {
+        let mut engine: LedPatterns<2> = LedPatterns::new();
+        engine.set_pattern(1, Pattern::On);
+        assert_eq!(engine.states(), [false, true]);
+        engine.tick(u32::MAX);
+        assert_eq!(engine.states(), [false, true]);
+        assert!(!engine.finished(1));
+    }
+
+    #[test]
+    fn blink() {
+        let states = trace(
+            Pattern::Blink {
+                period_ms: 10,
+                duty_percent: 30,
+                repeat: Repeat::Forever,
+            },
+            20,
+        );
+        for (ms, state) in states.iter().take(20).enumerate() {
+            assert_eq!(*state, ms % 10 < 3, "state at {} ms", ms);
+        }
+    }
+
+    #[test]
+    fn blink_long_period_does_not_overflow() {
+        let mut engine: LedPatterns<1> = LedPatterns::new();
+        engine.set_pattern(
+            0,
+            Pattern::Blink {
+                period_ms: 100_000_000,
+                duty_percent: 50,
+                repeat: Repeat::Forever,
+            },
+        );
+        engine.tick(49_999_999);
+        assert!(engine.is_on(0));
+        engine.tick(1);
+        assert!(!engine.is_on(0));
+    }
+
+    #[test]
+    fn blink_repeat_times() {
+        let mut engine: LedPatterns<1> = LedPatterns::new();
+        engine.set_pattern(
+            0,
+            Pattern::Blink {
+                period_ms: 10,
+                duty_percent: 50,
+                repeat: Repeat::Times(2),
+            },
+        );
+        engine.tick(19);
+        assert!(!engine.finished(0));
+        engine.tick(1);
+        assert!(engine.finished(0));
+        assert!(!engine.is_on(0));
+        engine.tick(5);
+        assert!(!engine.is_on(0));
+    }
+
+    #[test]
+    fn repeat_zero_times_is_off_immediately() {
+        let mut engine: LedPatterns<1> = LedPatterns::new();
+        engine.set_pattern(
+            0,
+            Pattern::Blink {
+                period_ms: 10,
+                duty_percent: 100,
+                repeat: Repeat::Times(0),
+            },
+        );
+        assert!(engine.finished(0));
+        assert!(!engine.is_on(0));
+    }
+
+    #[test]
+    fn heartbeat() {
+        let mut engine: LedPatterns<1> = LedPatterns::new();
+        engine.set_pattern(0, Pattern::Heartbeat { period_ms: 1000 });
+        let expected = [
+            (0, true),
+            (99, true),
+            (100, false),
+            (199, false),
+            (200, true),
+            (299, true),
+            (300, false),
+            (999, false),
+            (1000, true),
+        ];
+        let mut now = 0;
+        for (ms, on) in expected {
+            engine.tick(ms - now);
+            now = ms;
+            assert_eq!(engine.is_on(0), on, "state at {} ms", ms);
+        }
+    }
+
+    #[test]
+    fn sequence() {
+        static DURATIONS: [u32; 3] = [2, 1, 3];
+        let states = trace(
+            Pattern::Sequence {
+                durations_ms: &DURATIONS,
+                repeat: Repeat::Forever,
+            },
+            12,
+        );
+        assert_eq!(
+            states[..12],
+            [true, true, false, true, true, true, true, true, false, true, true, true]
+        );
+    }
+
+    #[test]
+    fn sequence_sum_saturates() {
+        static DURATIONS: [u32; 2] = [u32::MAX, 10];
+        let pattern = Pattern::Sequence {
+            durations_ms: &DURATIONS,
+            repeat: Repeat::Forever,
+        };
+        assert_eq!(pattern.cycle_ms(), Some(u32::MAX));
+    }
+}
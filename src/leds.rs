@@ -5,29 +5,174 @@
 //! - [LED example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/blinky-leds.rs)
 //! - [Button Blinky using IRQs](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/blinky-button-irq.rs)
 //! - [Button Blinky using IRQs and RTIC](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/blinky-button-rtic.rs)
+//! - [PWM LED example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/pwm-leds.rs)
+use core::convert::Infallible;
+use embedded_hal::{
+    digital::v2::{InputPin, OutputPin, StatefulOutputPin, ToggleableOutputPin},
+    PwmPin as HalPwmPin,
+};
 use va108xx_hal::{
-    gpio::dynpins::DynPin,
-    gpio::pins::{Pin, PushPullOutput, PA10, PA6, PA7},
-    prelude::*,
+    gpio::dynpins::{DynGroup, DynPin},
+    gpio::pins::{Pin, PinId, PinMode, PushPullOutput, PA10, PA6, PA7},
+    pac::{self, SYSCONFIG, TIM10, TIM6, TIM7},
+    pwm::{PwmPin, ReducedPwmPin},
+    time::{Hertz, MicroSeconds},
+    timer::{TimPin, ValidTim, ValidTimAndPin},
 };
 
+use crate::pulse::TimedPulsePin;
+
 pub type LD2 = Pin<PA10, PushPullOutput>;
 pub type LD3 = Pin<PA7, PushPullOutput>;
 pub type LD4 = Pin<PA6, PushPullOutput>;
 
-pub struct Leds {
-    leds: [Led; 3],
+/// Collection of `N` LEDs. The default of 3 matches the number of LEDs on the REB1 board.
+pub struct Leds<const N: usize = 3> {
+    leds: [Led; N],
+}
+
+/// Identifiers for the REB1 LEDs which match the silkscreen labels on the board
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum LedId {
+    /// LD2 on PA10
+    Ld2 = 0,
+    /// LD3 on PA7
+    Ld3 = 1,
+    /// LD4 on PA6
+    Ld4 = 2,
+}
+
+impl LedId {
+    pub const ALL: [LedId; 3] = [LedId::Ld2, LedId::Ld3, LedId::Ld4];
 }
 
 impl Leds {
+    /// Create the LEDs for the REB1 board. All REB1 LEDs are active low.
     pub fn new(led_pin1: LD2, led_pin2: LD3, led_pin3: LD4) -> Leds {
         Leds {
             leds: [led_pin1.into(), led_pin2.into(), led_pin3.into()],
         }
     }
+
+    pub fn ld2(&mut self) -> &mut Led {
+        &mut self[LedId::Ld2]
+    }
+
+    pub fn ld3(&mut self) -> &mut Led {
+        &mut self[LedId::Ld3]
+    }
+
+    pub fn ld4(&mut self) -> &mut Led {
+        &mut self[LedId::Ld4]
+    }
+
+    /// Iterate over all LEDs together with their identifier
+    pub fn iter_with_id(&mut self) -> impl Iterator<Item = (LedId, &mut Led)> {
+        LedId::ALL.into_iter().zip(self.leds.iter_mut())
+    }
+}
+
+impl core::ops::Index<LedId> for Leds {
+    type Output = Led;
+
+    fn index(&self, id: LedId) -> &Led {
+        &self.leds[id as usize]
+    }
+}
+
+impl core::ops::IndexMut<LedId> for Leds {
+    fn index_mut(&mut self, id: LedId) -> &mut Led {
+        &mut self.leds[id as usize]
+    }
+}
+
+impl<const N: usize> Leds<N> {
+    /// Create a LED collection from arbitrary LEDs, for example for carrier boards derived from
+    /// the REB1 board. See [Led::new].
+    pub fn from_leds(leds: [Led; N]) -> Self {
+        Leds { leds }
+    }
+
+    /// Turn on all LEDs whose bit is set in the mask and turn off all other LEDs. Bit 0
+    /// corresponds to the first LED, which is LD2 on the REB1 board, bit 1 to LD3 and bit 2 to
+    /// LD4. LEDs with an index larger than 31 can not be addressed with this function.
+    ///
+    /// Each port with LEDs is updated with two register writes: One write to the port set register
+    /// for the LEDs which are driven high, followed by one write to the port clear register for
+    /// the LEDs which are driven low. The two writes follow each other directly, so there is no
+    /// visible skew between the LEDs. With LEDs on both ports, four writes are required. The
+    /// current pin levels are not read, so the other pins of the port are never affected, even
+    /// if they are changed from an interrupt handler at the same time.
+    pub fn set_mask(&mut self, mask: u32) {
+        for group in [DynGroup::A, DynGroup::B] {
+            let (leds, active_high) = self.port_masks(group, u32::MAX);
+            if leds == 0 {
+                continue;
+            }
+            let (on, _) = self.port_masks(group, mask);
+            // Active high LEDs which are on and active low LEDs which are off are driven high
+            let high = (on & active_high) | (leds & !on & !active_high);
+            let port = port_reg(group);
+            // Safety: SETOUT and CLROUT are mask registers and only the bits of the LED pins are
+            // written
+            port.setout().write(|w| unsafe { w.bits(high) });
+            port.clrout().write(|w| unsafe { w.bits(leds & !high) });
+        }
+    }
+
+    /// Display the lower bits of the value as a binary number. The first LED shows the least
+    /// significant bit, so the three REB1 LEDs form a 3-bit counter. See [Self::set_mask] for
+    /// more details.
+    pub fn display_value(&mut self, value: u32) {
+        let bits = N.min(32);
+        let mask = if bits == 32 {
+            u32::MAX
+        } else {
+            (1 << bits) - 1
+        };
+        self.set_mask(value & mask);
+    }
+
+    /// Toggle all LEDs whose bit is set in the mask with a single write to the port toggle
+    /// register per port. See [Self::set_mask] for the bit assignment.
+    pub fn toggle_mask(&mut self, mask: u32) {
+        for group in [DynGroup::A, DynGroup::B] {
+            let (pins, _) = self.port_masks(group, mask);
+            if pins != 0 {
+                // Safety: TOGOUT is a mask register and only the bits of the LED pins are written
+                port_reg(group).togout().write(|w| unsafe { w.bits(pins) });
+            }
+        }
+    }
+
+    /// Convert a LED mask to the corresponding pin mask for the given port. Also returns the mask
+    /// of the active high pins among them.
+    fn port_masks(&self, group: DynGroup, mask: u32) -> (u32, u32) {
+        self.leds
+            .iter()
+            .enumerate()
+            .filter(|(idx, led)| {
+                *idx < 32 && (mask >> idx) & 0x01 == 1 && led.pin.id().group == group
+            })
+            .fold((0, 0), |(pins, active_high), (_, led)| {
+                let pin = 1 << led.pin.id().num;
+                match led.polarity {
+                    Polarity::ActiveHigh => (pins | pin, active_high | pin),
+                    Polarity::ActiveLow => (pins | pin, active_high),
+                }
+            })
+    }
 }
 
-impl core::ops::Deref for Leds {
+fn port_reg(group: DynGroup) -> &'static pac::porta::RegisterBlock {
+    // Safety: Only used for writes to mask registers
+    match group {
+        DynGroup::A => unsafe { &*pac::PORTA::ptr() },
+        DynGroup::B => unsafe { &*pac::PORTB::ptr() },
+    }
+}
+
+impl<const N: usize> core::ops::Deref for Leds<N> {
     type Target = [Led];
 
     fn deref(&self) -> &[Led] {
@@ -35,13 +180,13 @@ impl core::ops::Deref for Leds {
     }
 }
 
-impl core::ops::DerefMut for Leds {
+impl<const N: usize> core::ops::DerefMut for Leds<N> {
     fn deref_mut(&mut self) -> &mut [Led] {
         &mut self.leds
     }
 }
 
-impl core::ops::Index<usize> for Leds {
+impl<const N: usize> core::ops::Index<usize> for Leds<N> {
     type Output = Led;
 
     fn index(&self, i: usize) -> &Led {
@@ -49,14 +194,25 @@ impl core::ops::Index<usize> for Leds {
     }
 }
 
-impl core::ops::IndexMut<usize> for Leds {
+impl<const N: usize> core::ops::IndexMut<usize> for Leds<N> {
     fn index_mut(&mut self, i: usize) -> &mut Led {
         &mut self.leds[i]
     }
 }
 
+/// Pin level which turns the LED on
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Polarity {
+    /// The LED is on when the pin is low. This is the case for all REB1 LEDs
+    ActiveLow,
+    /// The LED is on when the pin is high
+    ActiveHigh,
+}
+
+/// A single LED. The LED state can be read back with [Led::is_on].
 pub struct Led {
     pin: DynPin,
+    polarity: Polarity,
 }
 
 macro_rules! ctor {
@@ -64,9 +220,7 @@ macro_rules! ctor {
 		$(
 			impl From<$ldx> for Led {
 				fn from(led: $ldx) -> Self {
-					Led {
-						pin: led.into()
-					}
+					Led::new(led, Polarity::ActiveLow)
 				}
 			}
 		)+
@@ -76,18 +230,261 @@ macro_rules! ctor {
 ctor!(LD2, LD3, LD4);
 
 impl Led {
-    /// Turns the LED off. Setting the pin high actually turns the LED off
+    /// Create a LED from any push-pull output pin on port A or port B.
+    pub fn new<I: PinId>(pin: Pin<I, PushPullOutput>, polarity: Polarity) -> Led {
+        // The input buffer is enabled so the pin level can be read back by Led::is_on
+        let mut pin: DynPin = pin.into();
+        pin.into_readable_push_pull_output();
+        Led { pin, polarity }
+    }
+
+    /// Pin level which turns the LED on
+    pub fn polarity(&self) -> Polarity {
+        self.polarity
+    }
+
+    /// Turns the LED off
     pub fn off(&mut self) {
-        self.pin.set_high().ok();
+        match self.polarity {
+            Polarity::ActiveLow => self.pin.set_high().ok(),
+            Polarity::ActiveHigh => self.pin.set_low().ok(),
+        };
     }
 
-    /// Turns the LED on. Setting the pin low actually turns the LED on
+    /// Turns the LED on
     pub fn on(&mut self) {
-        self.pin.set_low().ok();
+        match self.polarity {
+            Polarity::ActiveLow => self.pin.set_low().ok(),
+            Polarity::ActiveHigh => self.pin.set_high().ok(),
+        };
     }
 
     /// Toggles the LED
     pub fn toggle(&mut self) {
         self.pin.toggle().ok();
     }
+
+    /// Returns [true] if the LED is on. The pin level is read back through the input buffer of
+    /// the pin, so no shadow state is required.
+    pub fn is_on(&self) -> bool {
+        // The pin is always configured as a readable output, so the read can not fail
+        let high = self.pin.is_high().unwrap_or(false);
+        match self.polarity {
+            Polarity::ActiveLow => !high,
+            Polarity::ActiveHigh => high,
+        }
+    }
+
+    /// Returns [true] if the LED is off
+    pub fn is_off(&self) -> bool {
+        !self.is_on()
+    }
+
+    /// Convert the LED into a [TimedPulsePin] which lights the LED for the given time without
+    /// CPU involvement each time [TimedPulsePin::fire] is called, for example for activity
+    /// flashes. The pin is switched to the alternate function `M` which connects it to the
+    /// output of the timer, and the polarity of the LED is kept.
+    ///
+    /// The pin type is usually inferred from the pulse type aliases like
+    /// [LD2Pulse](crate::pulse::LD2Pulse), for example
+    /// `let pulse: LD2Pulse = ld2.into_timed_pulse(dp.TIM10, sys_clk, &mut dp.SYSCONFIG, width)`.
+    /// The LED and the timer are returned unchanged if the LED is not connected to the pin `I`.
+    pub fn into_timed_pulse<I, M, TIM>(
+        mut self,
+        tim: TIM,
+        sys_clk: impl Into<Hertz>,
+        sys_cfg: &mut SYSCONFIG,
+        width: impl Into<MicroSeconds>,
+    ) -> Result<TimedPulsePin<Pin<I, M>, TIM>, (Led, TIM)>
+    where
+        I: PinId,
+        M: PinMode,
+        TIM: ValidTim,
+        Pin<I, M>: TimPin,
+        (Pin<I, M>, TIM): ValidTimAndPin<Pin<I, M>, TIM>,
+    {
+        if self.pin.id() != I::DYN {
+            return Err((self, tim));
+        }
+        self.pin.into_mode(M::DYN);
+        let pin = match Pin::<I, M>::try_from(self.pin) {
+            Ok(pin) => pin,
+            // The ID and the mode were set above
+            Err(_) => unreachable!(),
+        };
+        Ok(TimedPulsePin::new(
+            (pin, tim),
+            sys_clk,
+            sys_cfg,
+            self.polarity,
+            width,
+        ))
+    }
+}
+
+/// The embedded-hal output traits use the logical LED state: Setting the pin high turns the LED
+/// on, even if the LED is active low and the physical pin is pulled low to do so.
+impl OutputPin for Led {
+    type Error = Infallible;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.on();
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.off();
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for Led {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_on())
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_off())
+    }
+}
+
+impl ToggleableOutputPin for Led {
+    type Error = Infallible;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Led::toggle(self);
+        Ok(())
+    }
+}
+
+/// LED which is driven by the PWM output of its associated timer. This allows setting the
+/// brightness of the LED.
+///
+/// The LEDs on the REB1 board are active low. The API of this type takes care of
+/// inverting the duty cycle, so a duty cycle of 0 always means that the LED is off
+/// while [u16::MAX] means the LED is fully on.
+pub struct PwmLed {
+    pwm: ReducedPwmPin,
+    duty: u16,
+}
+
+impl PwmLed {
+    fn new(mut pwm: ReducedPwmPin) -> Self {
+        HalPwmPin::set_duty(&mut pwm, u16::MAX);
+        HalPwmPin::enable(&mut pwm);
+        PwmLed { pwm, duty: 0 }
+    }
+
+    /// Set the LED duty cycle. 0 turns the LED off, [u16::MAX] turns it fully on.
+    pub fn set_duty(&mut self, duty: u16) {
+        self.duty = duty;
+        // Invert the duty cycle because the LED is lit while the pin is low
+        HalPwmPin::set_duty(&mut self.pwm, u16::MAX - duty);
+    }
+
+    /// Currently configured duty cycle. 0 means off, [u16::MAX] means fully on.
+    pub fn duty(&self) -> u16 {
+        self.duty
+    }
+
+    /// Set the brightness in percent. Values larger than 100 are treated as 100.
+    pub fn set_brightness(&mut self, percent: u8) {
+        let percent = percent.min(100) as u32;
+        self.set_duty((percent * u16::MAX as u32 / 100) as u16);
+    }
+
+    /// Currently configured brightness in percent
+    pub fn brightness(&self) -> u8 {
+        ((self.duty as u32 * 100 + u16::MAX as u32 / 2) / u16::MAX as u32) as u8
+    }
+
+    /// Turns the LED fully on
+    pub fn on(&mut self) {
+        self.set_duty(u16::MAX);
+    }
+
+    /// Turns the LED off
+    pub fn off(&mut self) {
+        self.set_duty(0);
+    }
+
+    /// Changes the PWM frequency. The configured duty cycle is preserved.
+    pub fn set_period(&mut self, freq: impl Into<Hertz>) {
+        self.pwm.set_period(freq);
+        self.set_duty(self.duty);
+    }
+}
+
+/// All three REB1 LEDs in PWM mode. The LED pins are routed to the PWM output of
+/// TIM10 (LD2), TIM7 (LD3) and TIM6 (LD4).
+pub struct PwmLeds {
+    leds: [PwmLed; 3],
+}
+
+impl PwmLeds {
+    /// Create the PWM LEDs. All LEDs are turned off initially.
+    ///
+    /// A PWM frequency of a few hundred Hz up to a few kHz is recommended to avoid visible flicker.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        led_pin1: LD2,
+        led_pin2: LD3,
+        led_pin3: LD4,
+        tim10: TIM10,
+        tim7: TIM7,
+        tim6: TIM6,
+        sys_clk: impl Into<Hertz> + Copy,
+        sys_cfg: &mut SYSCONFIG,
+        pwm_freq: impl Into<Hertz> + Copy,
+    ) -> PwmLeds {
+        PwmLeds {
+            leds: [
+                PwmLed::new(
+                    PwmPin::new(
+                        (led_pin1.into_funsel_1(), tim10),
+                        sys_clk,
+                        sys_cfg,
+                        pwm_freq,
+                    )
+                    .into(),
+                ),
+                PwmLed::new(
+                    PwmPin::new((led_pin2.into_funsel_1(), tim7), sys_clk, sys_cfg, pwm_freq)
+                        .into(),
+                ),
+                PwmLed::new(
+                    PwmPin::new((led_pin3.into_funsel_1(), tim6), sys_clk, sys_cfg, pwm_freq)
+                        .into(),
+                ),
+            ],
+        }
+    }
+}
+
+impl core::ops::Deref for PwmLeds {
+    type Target = [PwmLed];
+
+    fn deref(&self) -> &[PwmLed] {
+        &self.leds
+    }
+}
+
+impl core::ops::DerefMut for PwmLeds {
+    fn deref_mut(&mut self) -> &mut [PwmLed] {
+        &mut self.leds
+    }
+}
+
+impl core::ops::Index<usize> for PwmLeds {
+    type Output = PwmLed;
+
+    fn index(&self, i: usize) -> &PwmLed {
+        &self.leds[i]
+    }
+}
+
+impl core::ops::IndexMut<usize> for PwmLeds {
+    fn index_mut(&mut self, i: usize) -> &mut PwmLed {
+        &mut self.leds[i]
+    }
 }
@@ -1,6 +1,22 @@
 #![no_std]
 
+pub mod adxl343;
 pub mod button;
+pub mod debouncer;
+pub mod event_queue;
+pub mod events;
+pub mod i2c;
+pub mod led_patterns;
 pub mod leds;
 pub mod max11619;
+pub mod panic_code;
+#[cfg(feature = "panic-led")]
+pub mod panic_led;
+pub mod potentiometer;
+pub mod pulse;
+pub mod sensors;
+pub mod shared_i2c;
+pub mod sleep;
+pub mod temp_monitor;
 pub mod temp_sensor;
+pub mod temp_sensor_irq;
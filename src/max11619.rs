@@ -1,17 +1,28 @@
 //! This module provides a thin REB1 specific layer on top of the `max116xx_10bit` driver crate
 //!
+//! [Max11619Voltages] converts the raw conversion results of the ADC to millivolts and implements
+//! the [VoltageSensor] trait for all four analog inputs. The conversion takes the reference
+//! voltage of the configured [VoltageRefMode] into account and applies a gain and offset
+//! [Calibration] per channel in fixed point.
+//!
 //! ## Examples
 //!
 //! - [ADC example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/max11619-adc.rs)
+//! - [ADC voltages example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/max11619-voltages.rs)
 use core::convert::Infallible;
 use dummy_pin::DummyPin;
-use embedded_hal::{blocking::spi::Transfer, spi::FullDuplex};
+use embedded_hal::{
+    blocking::{delay::DelayUs, spi::Transfer},
+    spi::FullDuplex,
+};
 use max116xx_10bit::{
-    Error, ExternallyClocked, InternallyClockedInternallyTimedSerialInterface, Max116xx10Bit,
-    Max116xx10BitEocExt, VoltageRefMode, WithWakeupDelay, WithoutWakeupDelay,
+    AdcError, Error, ExternallyClocked, InternallyClockedInternallyTimedSerialInterface,
+    Max116xx10Bit, Max116xx10BitEocExt, VoltageRefMode, WithWakeupDelay, WithoutWakeupDelay,
 };
 use va108xx_hal::gpio::{Floating, Input, Pin, PA14};
 
+use crate::sensors::{Millivolts, VoltageSensor};
+
 pub type Max11619ExternallyClockedNoWakeup<SPI> =
     Max116xx10Bit<SPI, DummyPin, ExternallyClocked, WithoutWakeupDelay>;
 pub type Max11619ExternallyClockedWithWakeup<SPI> =
@@ -24,6 +35,14 @@ pub const AN0_CHANNEL: u8 = 0;
 pub const AN1_CHANNEL: u8 = 1;
 pub const AN2_CHANNEL: u8 = 2;
 pub const POTENTIOMETER_CHANNEL: u8 = 3;
+/// Number of analog inputs of the MAX11619
+pub const NUM_CHANNELS: u8 = 4;
+/// Number of codes of the 10-bit ADC
+const ADC_CODES: i32 = 1 << 10;
+/// Voltage of the internal reference of the MAX11619
+pub const INTERNAL_REF_MV: u16 = 2500;
+/// Number of fractional bits of the calibration gain
+const CALIBRATION_GAIN_SHIFT: u32 = 16;
 
 pub fn max11619_externally_clocked_no_wakeup<SpiE, SPI>(
     spi: SPI,
@@ -64,3 +83,451 @@ where
     adc.setup()?;
     Ok(adc)
 }
+
+/// Create the internally clocked configuration and wrap it into [Max11619Voltages] with the
+/// reference mode the ADC was set up with. The external reference voltage in millivolts is only
+/// used with [VoltageRefMode::ExternalSingleEndedNoWakeupDelay].
+pub fn max11619_internally_clocked_voltages<SpiE, SPI>(
+    spi: SPI,
+    eoc: EocPin,
+    v_ref: VoltageRefMode,
+    external_ref_mv: u16,
+) -> Result<Max11619Voltages<Max11619InternallyClocked<SPI, EocPin>>, Error<SpiE, Infallible>>
+where
+    SPI: Transfer<u8, Error = SpiE> + FullDuplex<u8, Error = SpiE>,
+{
+    let adc = max11619_internally_clocked(spi, eoc, v_ref)?;
+    Ok(Max11619Voltages::with_ref_mode(adc, v_ref, external_ref_mv))
+}
+
+/// Decode the reference mode from bits 3:2 of the SETUP register. The reserved value 0b11 also
+/// selects the internal reference which is always on.
+const fn ref_mode_from_setup_byte(setup: u8) -> VoltageRefMode {
+    match (setup >> 2) & 0b11 {
+        0b00 => VoltageRefMode::InternalRefWithWakeupDelay,
+        0b01 => VoltageRefMode::ExternalSingleEndedNoWakeupDelay,
+        _ => VoltageRefMode::InternalRefWithoutWakeupDelay,
+    }
+}
+
+/// MAX11619 configurations which can report the reference mode they were set up with
+pub trait ConfiguredRefMode {
+    fn ref_mode(&self) -> VoltageRefMode;
+}
+
+impl<SpiE, SPI> ConfiguredRefMode for Max11619ExternallyClockedNoWakeup<SPI>
+where
+    SPI: Transfer<u8, Error = SpiE> + FullDuplex<u8, Error = SpiE>,
+{
+    fn ref_mode(&self) -> VoltageRefMode {
+        ref_mode_from_setup_byte(self.get_setup_byte())
+    }
+}
+
+impl<SpiE, SPI> ConfiguredRefMode for Max11619ExternallyClockedWithWakeup<SPI>
+where
+    SPI: Transfer<u8, Error = SpiE> + FullDuplex<u8, Error = SpiE>,
+{
+    fn ref_mode(&self) -> VoltageRefMode {
+        ref_mode_from_setup_byte(self.get_setup_byte())
+    }
+}
+
+/// Blocking conversion of a single channel. The configuration with wakeup delay requires a delay
+/// provider and implements the trait when it is wrapped into a [Max11619WithDelay].
+pub trait ReadChannel {
+    type Error;
+
+    /// Returns the raw 10-bit conversion result
+    fn read_channel(&mut self, channel: u8) -> Result<u16, Self::Error>;
+}
+
+impl<SpiE, SPI> ReadChannel for Max11619ExternallyClockedNoWakeup<SPI>
+where
+    SPI: Transfer<u8, Error = SpiE> + FullDuplex<u8, Error = SpiE>,
+{
+    type Error = Error<SpiE, Infallible>;
+
+    fn read_channel(&mut self, channel: u8) -> Result<u16, Self::Error> {
+        let mut cmd_buf: [u8; 3] = [0; 3];
+        self.read_single_channel(&mut cmd_buf, channel)
+    }
+}
+
+impl<SpiE, SPI> ReadChannel for Max11619InternallyClocked<SPI, EocPin>
+where
+    SPI: Transfer<u8, Error = SpiE> + FullDuplex<u8, Error = SpiE>,
+{
+    type Error = Error<SpiE, Infallible>;
+
+    fn read_channel(&mut self, channel: u8) -> Result<u16, Self::Error> {
+        self.request_single_channel(channel)?;
+        nb::block!(self.get_single_channel())
+    }
+}
+
+/// [Max11619ExternallyClockedWithWakeup] together with the delay provider which is used to wait
+/// for the wakeup of the internal reference before each conversion
+pub struct Max11619WithDelay<SPI, DELAY> {
+    adc: Max11619ExternallyClockedWithWakeup<SPI>,
+    delay: DELAY,
+}
+
+impl<SPI, DELAY> Max11619WithDelay<SPI, DELAY> {
+    pub fn new(adc: Max11619ExternallyClockedWithWakeup<SPI>, delay: DELAY) -> Self {
+        Max11619WithDelay { adc, delay }
+    }
+
+    pub fn adc(&mut self) -> &mut Max11619ExternallyClockedWithWakeup<SPI> {
+        &mut self.adc
+    }
+
+    pub fn release(self) -> (Max11619ExternallyClockedWithWakeup<SPI>, DELAY) {
+        (self.adc, self.delay)
+    }
+}
+
+impl<SpiE, SPI, DELAY> ConfiguredRefMode for Max11619WithDelay<SPI, DELAY>
+where
+    SPI: Transfer<u8, Error = SpiE> + FullDuplex<u8, Error = SpiE>,
+{
+    fn ref_mode(&self) -> VoltageRefMode {
+        self.adc.ref_mode()
+    }
+}
+
+impl<SpiE, SPI, DELAY> ReadChannel for Max11619WithDelay<SPI, DELAY>
+where
+    SPI: Transfer<u8, Error = SpiE> + FullDuplex<u8, Error = SpiE>,
+    DELAY: DelayUs<u8>,
+{
+    type Error = Error<SpiE, Infallible>;
+
+    fn read_channel(&mut self, channel: u8) -> Result<u16, Self::Error> {
+        let mut cmd_buf: [u8; 3] = [0; 3];
+        self.adc
+            .read_single_channel(&mut cmd_buf, channel, &mut self.delay)
+    }
+}
+
+/// Gain and offset calibration of a single channel.
+///
+/// The calibrated voltage is `V_uncal * gain / 2^16 + offset`, so a gain of 65536 corresponds
+/// to a gain of 1. The calculation is done in microvolts with integer arithmetic.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Calibration {
+    /// Gain in the Q16.16 fixed-point format
+    pub gain_q16: i32,
+    pub offset_mv: i32,
+}
+
+impl Calibration {
+    /// Calibration which does not change the measured voltage
+    pub const IDENTITY: Calibration = Calibration {
+        gain_q16: 1 << CALIBRATION_GAIN_SHIFT,
+        offset_mv: 0,
+    };
+
+    pub const fn new(gain_q16: i32, offset_mv: i32) -> Self {
+        Calibration {
+            gain_q16,
+            offset_mv,
+        }
+    }
+
+    /// Calculate the calibration from two reference points. Each point consists of the
+    /// uncalibrated voltage read from the ADC and the actual voltage at the input. Returns
+    /// [None] if both points have the same uncalibrated voltage or if the gain does not fit into
+    /// the Q16.16 format.
+    pub fn from_two_points(
+        (measured_a, actual_a): (Millivolts, Millivolts),
+        (measured_b, actual_b): (Millivolts, Millivolts),
+    ) -> Option<Self> {
+        let measured_diff = measured_b.0 as i64 - measured_a.0 as i64;
+        if measured_diff == 0 {
+            return None;
+        }
+        let actual_diff = actual_b.0 as i64 - actual_a.0 as i64;
+        let gain_q16 =
+            i32::try_from((actual_diff << CALIBRATION_GAIN_SHIFT) / measured_diff).ok()?;
+        // Round the scaled voltage to the nearest millivolt
+        let scaled_a = (measured_a.0 as i64 * gain_q16 as i64
+            + (1 << (CALIBRATION_GAIN_SHIFT - 1)))
+            >> CALIBRATION_GAIN_SHIFT;
+        let offset_mv = i32::try_from(actual_a.0 as i64 - scaled_a).ok()?;
+        Some(Calibration {
+            gain_q16,
+            offset_mv,
+        })
+    }
+
+    /// Apply the calibration to an uncalibrated voltage in microvolts
+    fn apply_uv(&self, uncal_uv: i64) -> i64 {
+        ((uncal_uv * self.gain_q16 as i64) >> CALIBRATION_GAIN_SHIFT) + self.offset_mv as i64 * 1000
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Voltage measurements on the analog inputs of the MAX11619. The raw conversion results are
+/// converted to millivolts with the reference voltage of the configured [VoltageRefMode] and
+/// the [Calibration] of the channel.
+pub struct Max11619Voltages<ADC> {
+    adc: ADC,
+    ref_mode: VoltageRefMode,
+    ref_mv: u16,
+    calibration: [Calibration; NUM_CHANNELS as usize],
+}
+
+impl<ADC: ReadChannel + ConfiguredRefMode> Max11619Voltages<ADC> {
+    /// The reference mode is read from the configuration of the ADC. The external reference
+    /// voltage in millivolts is only used with [VoltageRefMode::ExternalSingleEndedNoWakeupDelay],
+    /// otherwise the internal reference voltage [INTERNAL_REF_MV] is used.
+    ///
+    /// The internally clocked configuration does not expose its reference mode and is created
+    /// with [max11619_internally_clocked_voltages] instead.
+    pub fn new(adc: ADC, external_ref_mv: u16) -> Self {
+        let ref_mode = adc.ref_mode();
+        Self::with_ref_mode(adc, ref_mode, external_ref_mv)
+    }
+}
+
+impl<ADC: ReadChannel> Max11619Voltages<ADC> {
+    fn with_ref_mode(adc: ADC, ref_mode: VoltageRefMode, external_ref_mv: u16) -> Self {
+        let ref_mv = match ref_mode {
+            VoltageRefMode::ExternalSingleEndedNoWakeupDelay => external_ref_mv,
+            VoltageRefMode::InternalRefWithWakeupDelay
+            | VoltageRefMode::InternalRefWithoutWakeupDelay => INTERNAL_REF_MV,
+        };
+        Max11619Voltages {
+            adc,
+            ref_mode,
+            ref_mv,
+            calibration: [Calibration::IDENTITY; NUM_CHANNELS as usize],
+        }
+    }
+
+    /// Set the calibration of all channels
+    pub fn with_calibration(mut self, calibration: [Calibration; NUM_CHANNELS as usize]) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    pub fn set_calibration(
+        &mut self,
+        channel: u8,
+        calibration: Calibration,
+    ) -> Result<(), AdcError> {
+        let cal = self
+            .calibration
+            .get_mut(channel as usize)
+            .ok_or(AdcError::InvalidChannel)?;
+        *cal = calibration;
+        Ok(())
+    }
+
+    pub fn calibration(&self, channel: u8) -> Option<&Calibration> {
+        self.calibration.get(channel as usize)
+    }
+
+    pub fn ref_mode(&self) -> VoltageRefMode {
+        self.ref_mode
+    }
+
+    /// Reference voltage in millivolts which corresponds to the full scale of the ADC
+    pub fn ref_mv(&self) -> u16 {
+        self.ref_mv
+    }
+
+    /// Convert a raw 10-bit conversion result of a channel to millivolts, rounded to the nearest
+    /// millivolt. Channels without a calibration use [Calibration::IDENTITY].
+    pub fn code_to_millivolts(&self, channel: u8, code: u16) -> Millivolts {
+        let cal = self
+            .calibration
+            .get(channel as usize)
+            .unwrap_or(&Calibration::IDENTITY);
+        let uncal_uv = code as i64 * self.ref_mv as i64 * 1000 / ADC_CODES as i64;
+        let uv = cal.apply_uv(uncal_uv);
+        let rounded = if uv < 0 {
+            (uv - 500) / 1000
+        } else {
+            (uv + 500) / 1000
+        };
+        Millivolts(rounded as i32)
+    }
+
+    /// Read the voltages of all four analog inputs
+    pub fn read_all_voltages(&mut self) -> Result<[Millivolts; NUM_CHANNELS as usize], ADC::Error> {
+        let mut voltages = [Millivolts::default(); NUM_CHANNELS as usize];
+        for (channel, voltage) in voltages.iter_mut().enumerate() {
+            *voltage = self.read_voltage(channel as u8)?;
+        }
+        Ok(voltages)
+    }
+
+    pub fn adc(&mut self) -> &mut ADC {
+        &mut self.adc
+    }
+
+    pub fn release(self) -> ADC {
+        self.adc
+    }
+}
+
+impl<ADC: ReadChannel> VoltageSensor for Max11619Voltages<ADC> {
+    type Error = ADC::Error;
+
+    fn num_channels(&self) -> u8 {
+        NUM_CHANNELS
+    }
+
+    fn read_voltage(&mut self, channel: u8) -> Result<Millivolts, Self::Error> {
+        let code = self.adc.read_channel(channel)?;
+        Ok(self.code_to_millivolts(channel, code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in ADC which returns a fixed code for every channel
+    struct FixedCode(u16);
+
+    impl ReadChannel for FixedCode {
+        type Error = Infallible;
+
+        fn read_channel(&mut self, _channel: u8) -> Result<u16, Infallible> {
+            Ok(self.0)
+        }
+    }
+
+    fn voltages(ref_mode: VoltageRefMode, external_ref_mv: u16) -> Max11619Voltages<FixedCode> {
+        Max11619Voltages::with_ref_mode(FixedCode(0), ref_mode, external_ref_mv)
+    }
+
+    #[test]
+    fn ref_mode_decoding() {
+        assert_eq!(
+            ref_mode_from_setup_byte(0b0110_0000),
+            VoltageRefMode::InternalRefWithWakeupDelay
+        );
+        assert_eq!(
+            ref_mode_from_setup_byte(0b0110_0100),
+            VoltageRefMode::ExternalSingleEndedNoWakeupDelay
+        );
+        assert_eq!(
+            ref_mode_from_setup_byte(0b0110_1000),
+            VoltageRefMode::InternalRefWithoutWakeupDelay
+        );
+        assert_eq!(
+            ref_mode_from_setup_byte(0b0110_1100),
+            VoltageRefMode::InternalRefWithoutWakeupDelay
+        );
+    }
+
+    #[test]
+    fn reference_voltage() {
+        assert_eq!(
+            voltages(VoltageRefMode::ExternalSingleEndedNoWakeupDelay, 3300).ref_mv(),
+            3300
+        );
+        assert_eq!(
+            voltages(VoltageRefMode::InternalRefWithWakeupDelay, 3300).ref_mv(),
+            INTERNAL_REF_MV
+        );
+        assert_eq!(
+            voltages(VoltageRefMode::InternalRefWithoutWakeupDelay, 3300).ref_mv(),
+            INTERNAL_REF_MV
+        );
+    }
+
+    #[test]
+    fn uncalibrated_conversion_rounds_to_nearest() {
+        let adc = voltages(VoltageRefMode::InternalRefWithWakeupDelay, 0);
+        assert_eq!(adc.code_to_millivolts(0, 0), Millivolts(0));
+        // 2500 mV / 1024 = 2.441 mV per code
+        assert_eq!(adc.code_to_millivolts(0, 1), Millivolts(2));
+        assert_eq!(adc.code_to_millivolts(0, 3), Millivolts(7));
+        assert_eq!(adc.code_to_millivolts(0, 512), Millivolts(1250));
+        assert_eq!(adc.code_to_millivolts(0, 1023), Millivolts(2498));
+    }
+
+    #[test]
+    fn negative_results_round_away_from_zero() {
+        let mut adc = voltages(VoltageRefMode::InternalRefWithWakeupDelay, 0);
+        adc.set_calibration(0, Calibration::new(-(1 << 16), 0))
+            .unwrap();
+        assert_eq!(adc.code_to_millivolts(0, 1), Millivolts(-2));
+        assert_eq!(adc.code_to_millivolts(0, 3), Millivolts(-7));
+        assert!(matches!(
+            adc.set_calibration(NUM_CHANNELS, Calibration::IDENTITY),
+            Err(AdcError::InvalidChannel)
+        ));
+    }
+
+    #[test]
+    fn calibration_is_applied_per_channel() {
+        let mut adc = voltages(VoltageRefMode::ExternalSingleEndedNoWakeupDelay, 3300);
+        adc.adc().0 = 512;
+        adc.set_calibration(1, Calibration::new(2 << 16, -100))
+            .unwrap();
+        assert_eq!(adc.read_voltage(0), Ok(Millivolts(1650)));
+        assert_eq!(adc.read_voltage(1), Ok(Millivolts(3200)));
+        let all = adc.read_all_voltages().unwrap();
+        assert_eq!(all[1], Millivolts(3200));
+        assert_eq!(all[3], Millivolts(1650));
+    }
+
+    #[test]
+    fn calibration_from_two_points() {
+        let cal = Calibration::from_two_points(
+            (Millivolts(100), Millivolts(110)),
+            (Millivolts(2100), Millivolts(2310)),
+        )
+        .unwrap();
+        assert_eq!(cal.gain_q16, (11 << 16) / 10);
+        assert_eq!(cal.offset_mv, 0);
+        let offset = Calibration::from_two_points(
+            (Millivolts(0), Millivolts(-20)),
+            (Millivolts(1000), Millivolts(980)),
+        )
+        .unwrap();
+        assert_eq!(offset, Calibration::new(1 << 16, -20));
+        // The reference points map back onto the actual voltages after rounding
+        assert_eq!((cal.apply_uv(100_000) + 500) / 1000, 110);
+        assert_eq!((cal.apply_uv(2_100_000) + 500) / 1000, 2310);
+        assert_eq!(offset.apply_uv(1_000_000), 980_000);
+    }
+
+    #[test]
+    fn invalid_calibration_points() {
+        assert_eq!(
+            Calibration::from_two_points(
+                (Millivolts(500), Millivolts(400)),
+                (Millivolts(500), Millivolts(600)),
+            ),
+            None
+        );
+        // A gain of 200000 does not fit into Q16.16
+        assert_eq!(
+            Calibration::from_two_points(
+                (Millivolts(0), Millivolts(0)),
+                (Millivolts(1), Millivolts(200_000)),
+            ),
+            None
+        );
+        assert_eq!(
+            Calibration::from_two_points(
+                (Millivolts(i32::MIN), Millivolts(i32::MAX)),
+                (Millivolts(i32::MAX), Millivolts(i32::MIN)),
+            )
+            .map(|cal| cal.gain_q16),
+            Some(-(1 << 16))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! # Error codes of the panic LED handler
+//!
+//! The `panic-led` feature provides a panic handler which blinks out a 12 bit error code derived
+//! from the panic location. This module contains the code computation without the panic handler,
+//! so it is always available. A host tool or test can use [location_code] to recompute the codes
+//! for all source lines of a firmware and find the location of a panic.
+//!
+//! Please note that the file name must be passed exactly like the compiler reports it in the
+//! panic location, which is usually the path relative to the crate root of the panicking crate.
+
+/// Mask for the valid bits of an error code
+pub const CODE_MASK: u16 = 0xfff;
+
+/// Compute the 12 bit error code for a panic location. This is a FNV-1a hash over the file name
+/// and the little endian line number, folded to 12 bits.
+pub const fn location_code(file: &str, line: u32) -> u16 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let file = file.as_bytes();
+    let line = line.to_le_bytes();
+    let mut idx = 0;
+    while idx < file.len() + line.len() {
+        let byte = if idx < file.len() {
+            file[idx]
+        } else {
+            line[idx - file.len()]
+        };
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        idx += 1;
+    }
+    ((hash ^ (hash >> 12) ^ (hash >> 24)) as u16) & CODE_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference implementation of the hash with iterators
+    fn reference(file: &str, line: u32) -> u16 {
+        let mut hash: u32 = 0x811c_9dc5;
+        for byte in file.bytes().chain(line.to_le_bytes()) {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+        ((hash ^ (hash >> 12) ^ (hash >> 24)) as u16) & CODE_MASK
+    }
+
+    #[test]
+    fn fnv1a_of_empty_input() {
+        // FNV-1a of the four zero bytes of line 0 is 0x4b95f515
+        let hash: u32 = 0x4b95_f515;
+        let folded = ((hash ^ (hash >> 12) ^ (hash >> 24)) as u16) & CODE_MASK;
+        assert_eq!(location_code("", 0), folded);
+    }
+
+    #[test]
+    fn matches_reference() {
+        for (file, line) in [
+            ("src/main.rs", 1),
+            ("src/main.rs", 2),
+            ("examples/blinky-leds.rs", 42),
+            ("src/bin/app.rs", u32::MAX),
+        ] {
+            assert_eq!(location_code(file, line), reference(file, line));
+        }
+    }
+
+    #[test]
+    fn code_fits_in_12_bits() {
+        for line in 0..1000 {
+            assert_eq!(location_code("src/main.rs", line) & !CODE_MASK, 0);
+        }
+    }
+
+    #[test]
+    fn usable_in_const_context() {
+        const CODE: u16 = location_code("src/main.rs", 10);
+        assert_eq!(CODE, reference("src/main.rs", 10));
+    }
+}
@@ -0,0 +1,174 @@
+//! # Panic handler which blinks out an error code on the REB1 LEDs
+//!
+//! This module is only available with the `panic-led` feature. It provides a `#[panic_handler]`,
+//! so other panic handler crates like `panic-halt` or `panic-rtt-target` must not be linked into
+//! the same application.
+//!
+//! When a panic occurs, interrupts are disabled and the LEDs LD2 (PA10), LD3 (PA7) and LD4 (PA6)
+//! are taken over directly via the PAC, even if they were in use by the application or
+//! configured for PWM. The handler then loops forever and blinks an error code. This allows
+//! reading a crash from the board alone when no debugger is attached.
+//!
+//! ## Error code
+//!
+//! The error code is a 12 bit value. If the application has registered a code with
+//! [set_panic_code], that code is shown. Otherwise, the code is derived from the panic location
+//! with [location_code]. The location code can be recomputed on the host for all source lines of
+//! a firmware to find the panic location. [location_code] is defined in the
+//! [panic_code](crate::panic_code) module, which is also available without the `panic-led`
+//! feature, so it can be called in a small host tool or test.
+//!
+//! ## Encoding
+//!
+//! The code is shown as four octal digits, most significant digit first. Each digit is shown as a
+//! 3-bit binary number, where a lit LED represents a 1:
+//!
+//! | LED | PA10 (LD2) | PA7 (LD3) | PA6 (LD4) |
+//! |-----|------------|-----------|-----------|
+//! | Bit | 2 (4)      | 1 (2)     | 0 (1)     |
+//!
+//! One frame looks like this and is repeated forever:
+//!
+//! 1. Start marker for 2 seconds: All three LEDs blink quickly for a location code, only LD4
+//!    blinks quickly for a user registered code.
+//! 2. All LEDs off for 1 second.
+//! 3. Four digits, each shown for 1 second followed by 0.5 seconds with all LEDs off. A digit
+//!    of 0 is therefore shown as 1.5 seconds with all LEDs off.
+//! 4. All LEDs off for 2 seconds.
+//!
+//! For example, the code 0o1057 is shown as LD4 on, all LEDs off, LD2 and LD4 on, and all LEDs on.
+//!
+//! The blink timing is derived from the system clock set with
+//! [`va108xx_hal::clock::set_sys_clock`]. If it was not set, a system clock of 50 MHz is assumed,
+//! which is the frequency of the REB1 oscillator.
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use va108xx_hal::{
+    clock::{enable_peripheral_clock, get_sys_clock, PeripheralClocks},
+    pac,
+};
+
+pub use crate::panic_code::{location_code, CODE_MASK};
+
+const REB1_SYS_CLK_HZ: u32 = 50_000_000;
+const NO_USER_CODE: u32 = u32::MAX;
+const LED_D2: u32 = 1 << 10;
+const LED_D3: u32 = 1 << 7;
+const LED_D4: u32 = 1 << 6;
+const ALL_LEDS: u32 = LED_D2 | LED_D3 | LED_D4;
+
+static USER_CODE: AtomicU32 = AtomicU32::new(NO_USER_CODE);
+
+/// Register an error code which is blinked out instead of the location code if a panic occurs.
+/// Only the lower 12 bits are used.
+pub fn set_panic_code(code: u16) {
+    USER_CODE.store((code & CODE_MASK) as u32, Ordering::Relaxed);
+}
+
+/// Remove a previously registered error code. The location code will be shown again.
+pub fn clear_panic_code() {
+    USER_CODE.store(NO_USER_CODE, Ordering::Relaxed);
+}
+
+/// Convert an octal digit to the PORTA LED bit mask
+fn digit_mask(digit: u16) -> u32 {
+    let mut mask = 0;
+    if digit & 0b100 != 0 {
+        mask |= LED_D2;
+    }
+    if digit & 0b010 != 0 {
+        mask |= LED_D3;
+    }
+    if digit & 0b001 != 0 {
+        mask |= LED_D4;
+    }
+    mask
+}
+
+struct PanicLeds {
+    porta: pac::PORTA,
+    cycles_per_ms: u32,
+}
+
+impl PanicLeds {
+    fn show(&self, lit: u32, ms: u32) {
+        // The LEDs are active low
+        self.porta
+            .setout()
+            .write(|w| unsafe { w.bits(ALL_LEDS & !lit) });
+        self.porta.clrout().write(|w| unsafe { w.bits(lit) });
+        for _ in 0..ms {
+            cortex_m::asm::delay(self.cycles_per_ms);
+        }
+    }
+
+    fn frame(&self, code: u16, user_code: bool) {
+        let marker = if user_code { LED_D4 } else { ALL_LEDS };
+        for _ in 0..10 {
+            self.show(marker, 100);
+            self.show(0, 100);
+        }
+        self.show(0, 1000);
+        for shift in [9, 6, 3, 0] {
+            self.show(digit_mask((code >> shift) & 0b111), 1000);
+            self.show(0, 500);
+        }
+        self.show(0, 2000);
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let (code, user_code) = match USER_CODE.load(Ordering::Relaxed) {
+        NO_USER_CODE => (
+            info.location()
+                .map(|loc| location_code(loc.file(), loc.line()))
+                .unwrap_or(0),
+            false,
+        ),
+        code => (code as u16, true),
+    };
+    // Safety: The application is not running anymore, so the handler is the only user of the
+    // peripherals from here on
+    let mut dp = unsafe { pac::Peripherals::steal() };
+    for clk in [
+        PeripheralClocks::PortA,
+        PeripheralClocks::Ioconfig,
+        PeripheralClocks::Gpio,
+    ] {
+        enable_peripheral_clock(&mut dp.SYSCONFIG, clk);
+    }
+    // Reset the pin configuration to plain GPIO, the LEDs might have been used with PWM
+    for pin in [10, 7, 6] {
+        dp.IOCONFIG.porta[pin].reset();
+    }
+    dp.PORTA
+        .datamask()
+        .modify(|r, w| unsafe { w.bits(r.bits() | ALL_LEDS) });
+    // Disable the GPIO pulse mode and the output delays, otherwise the writes below would only
+    // generate single clock pulses
+    dp.PORTA
+        .pulse()
+        .modify(|r, w| unsafe { w.bits(r.bits() & !ALL_LEDS) });
+    dp.PORTA
+        .delay1()
+        .modify(|r, w| unsafe { w.bits(r.bits() & !ALL_LEDS) });
+    dp.PORTA
+        .delay2()
+        .modify(|r, w| unsafe { w.bits(r.bits() & !ALL_LEDS) });
+    dp.PORTA.setout().write(|w| unsafe { w.bits(ALL_LEDS) });
+    dp.PORTA
+        .dir()
+        .modify(|r, w| unsafe { w.bits(r.bits() | ALL_LEDS) });
+    let sys_clk = get_sys_clock().map(|clk| clk.0).unwrap_or(REB1_SYS_CLK_HZ);
+    let leds = PanicLeds {
+        porta: dp.PORTA,
+        cycles_per_ms: sys_clk / 1000,
+    };
+    loop {
+        leds.frame(code, user_code);
+    }
+}
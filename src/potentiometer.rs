@@ -0,0 +1,253 @@
+//! # Potentiometer of the REB1 board
+//!
+//! The potentiometer is connected to the [POTENTIOMETER_CHANNEL] of the MAX11619 ADC. The raw
+//! conversion results jitter by a few codes, so [Potentiometer] filters them before they are
+//! mapped to a knob position in per mille:
+//!
+//! 1. An exponential moving average smooths the raw codes
+//! 2. A deadband at both ends of the range makes sure 0 ‰ and 1000 ‰ can be reached
+//! 3. The output position only changes if the filtered position differs by more than the
+//!    hysteresis, or if one of the ends is reached
+//!
+//! The potentiometer does not own the ADC, so the other analog inputs can still be read. Only
+//! integer arithmetic is used.
+//!
+//! ## Examples
+//!
+//! - [Potentiometer example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/potentiometer.rs)
+use crate::max11619::{ReadChannel, POTENTIOMETER_CHANNEL};
+
+/// Highest code of the 10-bit ADC
+const ADC_MAX_CODE: u16 = (1 << 10) - 1;
+/// Number of fractional bits of the filtered value
+const FILTER_FRAC_BITS: u32 = 8;
+/// Position at the upper end of the range
+pub const FULL_SCALE_PERMILLE: u16 = 1000;
+/// Largest supported [PotConfig::smoothing_shift]. A new sample is still weighed with at least
+/// one fractional bit of the filter.
+pub const MAX_SMOOTHING_SHIFT: u8 = FILTER_FRAC_BITS as u8;
+/// Largest supported [PotConfig::deadband], which leaves a range of two codes between the ends
+pub const MAX_DEADBAND: u16 = ADC_MAX_CODE / 2;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PotConfig {
+    /// Number of codes at each end of the ADC range which are mapped to 0 ‰ and 1000 ‰. Values
+    /// above [MAX_DEADBAND] are clamped.
+    pub deadband: u16,
+    /// Minimum change of the position in per mille before the output is updated
+    pub hysteresis_permille: u16,
+    /// The moving average weighs a new sample with 1 / 2^smoothing_shift. 0 disables the
+    /// smoothing. Values above [MAX_SMOOTHING_SHIFT] are clamped.
+    pub smoothing_shift: u8,
+}
+
+impl Default for PotConfig {
+    fn default() -> Self {
+        PotConfig {
+            deadband: 8,
+            hysteresis_permille: 4,
+            smoothing_shift: 2,
+        }
+    }
+}
+
+pub struct Potentiometer {
+    cfg: PotConfig,
+    channel: u8,
+    /// Filtered ADC code with [FILTER_FRAC_BITS] fractional bits
+    filtered: Option<u32>,
+    position: u16,
+}
+
+impl Potentiometer {
+    /// The deadband and the smoothing shift of the configuration are clamped to their maximum
+    /// values
+    pub const fn new(mut cfg: PotConfig) -> Self {
+        if cfg.deadband > MAX_DEADBAND {
+            cfg.deadband = MAX_DEADBAND;
+        }
+        if cfg.smoothing_shift > MAX_SMOOTHING_SHIFT {
+            cfg.smoothing_shift = MAX_SMOOTHING_SHIFT;
+        }
+        Potentiometer {
+            cfg,
+            channel: POTENTIOMETER_CHANNEL,
+            filtered: None,
+            position: 0,
+        }
+    }
+
+    /// Use a different ADC channel, for example on boards derived from the REB1
+    pub const fn with_channel(mut self, channel: u8) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn config(&self) -> &PotConfig {
+        &self.cfg
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Read the potentiometer channel and update the position. Returns [true] if the knob moved.
+    pub fn update<ADC: ReadChannel>(&mut self, adc: &mut ADC) -> Result<bool, ADC::Error> {
+        let code = adc.read_channel(self.channel)?;
+        Ok(self.update_raw(code))
+    }
+
+    /// Update the position with a raw 10-bit conversion result. Returns [true] if the knob moved.
+    pub fn update_raw(&mut self, code: u16) -> bool {
+        let sample = (code.min(ADC_MAX_CODE) as u32) << FILTER_FRAC_BITS;
+        let filtered = match self.filtered {
+            // The first sample initializes the filter and always counts as a movement
+            None => {
+                self.filtered = Some(sample);
+                self.position = self.to_permille(sample);
+                return true;
+            }
+            Some(prev) => {
+                let shift = self.cfg.smoothing_shift as u32;
+                if sample >= prev {
+                    prev + ((sample - prev) >> shift)
+                } else {
+                    prev - ((prev - sample) >> shift)
+                }
+            }
+        };
+        self.filtered = Some(filtered);
+        let position = self.to_permille(filtered);
+        let at_end = position == 0 || position == FULL_SCALE_PERMILLE;
+        if position.abs_diff(self.position) > self.cfg.hysteresis_permille
+            || (at_end && position != self.position)
+        {
+            self.position = position;
+            return true;
+        }
+        false
+    }
+
+    /// Map a filtered code to per mille, rounded to the nearest value
+    fn to_permille(&self, filtered: u32) -> u16 {
+        let low = (self.cfg.deadband as u32) << FILTER_FRAC_BITS;
+        let high = ((ADC_MAX_CODE - self.cfg.deadband) as u32) << FILTER_FRAC_BITS;
+        if filtered <= low {
+            return 0;
+        }
+        if filtered >= high {
+            return FULL_SCALE_PERMILLE;
+        }
+        let span = high - low;
+        (((filtered - low) * FULL_SCALE_PERMILLE as u32 + span / 2) / span) as u16
+    }
+
+    /// Stable position of the knob between 0 ‰ and 1000 ‰
+    pub fn position(&self) -> u16 {
+        self.position
+    }
+
+    /// Position in percent, rounded down
+    pub fn percent(&self) -> u8 {
+        (self.position / 10) as u8
+    }
+
+    /// Reset the filter, so the next sample is used as the initial value
+    pub fn reset(&mut self) {
+        self.filtered = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pot(deadband: u16, hysteresis_permille: u16, smoothing_shift: u8) -> Potentiometer {
+        Potentiometer::new(PotConfig {
+            deadband,
+            hysteresis_permille,
+            smoothing_shift,
+        })
+    }
+
+    #[test]
+    fn first_sample_initializes_the_filter() {
+        let mut pot = pot(0, 4, 2);
+        assert!(pot.update_raw(512));
+        assert_eq!(pot.position(), 500);
+        assert_eq!(pot.percent(), 50);
+        // The same code does not move the knob
+        assert!(!pot.update_raw(512));
+        pot.reset();
+        assert!(pot.update_raw(100));
+        assert_eq!(pot.position(), 98);
+    }
+
+    #[test]
+    fn hysteresis_threshold() {
+        let mut pot = pot(0, 4, 0);
+        pot.update_raw(512);
+        // 4 codes are 3.9 ‰, which is within the hysteresis
+        assert!(!pot.update_raw(516));
+        assert!(!pot.update_raw(508));
+        assert_eq!(pot.position(), 500);
+        // 5 codes are 4.9 ‰ and exceed the hysteresis
+        assert!(pot.update_raw(517));
+        assert_eq!(pot.position(), 505);
+    }
+
+    #[test]
+    fn ends_are_reached_despite_hysteresis() {
+        let mut pot = pot(0, 20, 0);
+        pot.update_raw(1013);
+        assert_eq!(pot.position(), 990);
+        assert!(pot.update_raw(1023));
+        assert_eq!(pot.position(), FULL_SCALE_PERMILLE);
+        pot.update_raw(10);
+        assert_eq!(pot.position(), 10);
+        assert!(pot.update_raw(0));
+        assert_eq!(pot.position(), 0);
+    }
+
+    #[test]
+    fn smoothing_converges_to_the_ends() {
+        let mut pot = pot(8, 4, MAX_SMOOTHING_SHIFT);
+        pot.update_raw(0);
+        for _ in 0..4000 {
+            pot.update_raw(1023);
+        }
+        assert_eq!(pot.position(), FULL_SCALE_PERMILLE);
+        for _ in 0..4000 {
+            pot.update_raw(0);
+        }
+        assert_eq!(pot.position(), 0);
+    }
+
+    #[test]
+    fn deadband_maps_to_the_ends() {
+        let mut pot = pot(8, 0, 0);
+        pot.update_raw(8);
+        assert_eq!(pot.position(), 0);
+        pot.update_raw(9);
+        assert_eq!(pot.position(), 1);
+        pot.update_raw(1015);
+        assert_eq!(pot.position(), FULL_SCALE_PERMILLE);
+        pot.update_raw(1014);
+        assert_eq!(pot.position(), 999);
+    }
+
+    #[test]
+    fn config_is_clamped() {
+        let smoothed = pot(600, 4, 40);
+        assert_eq!(smoothed.config().deadband, MAX_DEADBAND);
+        assert_eq!(smoothed.config().smoothing_shift, MAX_SMOOTHING_SHIFT);
+        let mut unsmoothed = pot(600, 4, 0);
+        unsmoothed.update_raw(511);
+        assert_eq!(unsmoothed.position(), 0);
+        unsmoothed.update_raw(512);
+        assert_eq!(unsmoothed.position(), FULL_SCALE_PERMILLE);
+        // Codes above the 10-bit range are treated as the highest code
+        unsmoothed.update_raw(u16::MAX);
+        assert_eq!(unsmoothed.position(), FULL_SCALE_PERMILLE);
+    }
+}
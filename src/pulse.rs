@@ -0,0 +1,179 @@
+//! # Hardware timed pulses on LEDs and header pins
+//!
+//! Two different pulse generators are provided:
+//!
+//! - [GpioPulsePin] uses the pulse mode of the GPIO block. Writing the non-default state to the
+//!   pin generates a pulse of exactly one system clock cycle. The output can additionally be
+//!   delayed by up to three clock cycles with the programmable output delay. This is useful for
+//!   precise trigger pulses to lab equipment.
+//! - [TimedPulsePin] routes the pin to the output of its associated timer. The timer is
+//!   configured to drive the pin while it is running and to disable itself when it reaches 0, so
+//!   a pulse of a configurable width is generated without any CPU involvement after
+//!   [TimedPulsePin::fire] was called. This is useful for visible activity flashes on the
+//!   REB1 LEDs. A [Led](crate::leds::Led) can be converted with
+//!   [Led::into_timed_pulse](crate::leds::Led::into_timed_pulse).
+use crate::leds::Polarity;
+use va108xx_hal::{
+    gpio::{
+        dynpins::DynPin,
+        pins::{AltFunc1, Pin, PinId, PinState, PushPullOutput, PA10, PA6, PA7},
+    },
+    pac::{self, tim0, SYSCONFIG, TIM10, TIM6, TIM7},
+    prelude::*,
+    time::{Hertz, MicroSeconds},
+    timer::{TimPin, ValidTim, ValidTimAndPin},
+};
+
+/// Timed pulse on LD2. The pin needs to be configured with [Pin::into_funsel_1].
+pub type LD2Pulse = TimedPulsePin<Pin<PA10, AltFunc1>, TIM10>;
+/// Timed pulse on LD3. The pin needs to be configured with [Pin::into_funsel_1].
+pub type LD3Pulse = TimedPulsePin<Pin<PA7, AltFunc1>, TIM7>;
+/// Timed pulse on LD4. The pin needs to be configured with [Pin::into_funsel_1].
+pub type LD4Pulse = TimedPulsePin<Pin<PA6, AltFunc1>, TIM6>;
+
+/// Programmable output delay of the GPIO block in system clock cycles
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum OutputDelay {
+    None,
+    OneCycle,
+    TwoCycles,
+    ThreeCycles,
+}
+
+impl OutputDelay {
+    fn delay_bits(self) -> (bool, bool) {
+        match self {
+            OutputDelay::None => (false, false),
+            OutputDelay::OneCycle => (true, false),
+            OutputDelay::TwoCycles => (false, true),
+            OutputDelay::ThreeCycles => (true, true),
+        }
+    }
+}
+
+/// Pin configured for the GPIO pulse mode. Each call to [GpioPulsePin::fire] generates a pulse
+/// with a width of one system clock cycle.
+pub struct GpioPulsePin {
+    pin: DynPin,
+    idle_high: bool,
+}
+
+impl GpioPulsePin {
+    /// Configure a push-pull output pin for the pulse mode. The pin rests in the given default
+    /// state and the pulse is delayed by the given number of clock cycles.
+    pub fn new<I: PinId>(
+        pin: Pin<I, PushPullOutput>,
+        default_state: PinState,
+        delay: OutputDelay,
+    ) -> Self {
+        let idle_high = default_state == PinState::High;
+        let (delay_1, delay_2) = delay.delay_bits();
+        let mut pin: DynPin = pin
+            .delay(delay_1, delay_2)
+            .pulse_mode(true, default_state)
+            .into();
+        if idle_high {
+            pin.set_high().ok();
+        } else {
+            pin.set_low().ok();
+        }
+        GpioPulsePin { pin, idle_high }
+    }
+
+    /// Generate a single pulse. The pin returns to its default state by itself.
+    pub fn fire(&mut self) {
+        if self.idle_high {
+            self.pin.set_low().ok();
+        } else {
+            self.pin.set_high().ok();
+        }
+    }
+}
+
+/// Pin driven by its associated timer to generate pulses of a configurable width.
+///
+/// The pin must be in the alternate function which connects it to the timer output, similar to
+/// the PWM API of the HAL.
+pub struct TimedPulsePin<PIN: TimPin, TIM: ValidTim> {
+    pin: PIN,
+    tim: TIM,
+    sys_clk: Hertz,
+    width_cycles: u32,
+}
+
+impl<PIN: TimPin, TIM: ValidTim> TimedPulsePin<PIN, TIM>
+where
+    (PIN, TIM): ValidTimAndPin<PIN, TIM>,
+{
+    /// Create a new timed pulse pin with the given pulse width. The polarity specifies the level
+    /// of the pin during the pulse. For the active low REB1 LEDs, [Polarity::ActiveLow] lights
+    /// the LED for the duration of the pulse.
+    pub fn new(
+        vtp: (PIN, TIM),
+        sys_clk: impl Into<Hertz>,
+        sys_cfg: &mut SYSCONFIG,
+        polarity: Polarity,
+        width: impl Into<MicroSeconds>,
+    ) -> Self {
+        sys_cfg
+            .tim_clk_enable
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << TIM::TIM_ID)) });
+        let mut pulse_pin = TimedPulsePin {
+            pin: vtp.0,
+            tim: vtp.1,
+            sys_clk: sys_clk.into(),
+            width_cycles: 0,
+        };
+        pulse_pin.reg().ctrl.write(|w| {
+            w.auto_disable().set_bit();
+            w.status_inv().bit(polarity == Polarity::ActiveLow);
+            // The output is driven while the timer is enabled
+            w.status_sel().enabled()
+        });
+        pulse_pin.set_width(width);
+        pulse_pin
+    }
+
+    /// All 24 TIM blocks are identical and placed next to each other in memory
+    fn reg(&self) -> &tim0::RegisterBlock {
+        unsafe { &*pac::TIM0::ptr().offset(TIM::TIM_ID as isize) }
+    }
+
+    /// Set the pulse width. The resolution is one system clock cycle.
+    pub fn set_width(&mut self, width: impl Into<MicroSeconds>) {
+        let cycles = (self.sys_clk.0 as u64 * width.into().0 as u64) / 1_000_000;
+        self.set_width_cycles(cycles.min(u32::MAX as u64) as u32);
+    }
+
+    /// Set the pulse width in system clock cycles
+    pub fn set_width_cycles(&mut self, cycles: u32) {
+        self.width_cycles = cycles.max(1);
+        self.reg()
+            .rst_value
+            .write(|w| unsafe { w.bits(self.width_cycles) });
+    }
+
+    /// Configured pulse width in system clock cycles
+    pub fn width_cycles(&self) -> u32 {
+        self.width_cycles
+    }
+
+    /// Start a pulse. If a pulse is still active, it is restarted with the full width.
+    pub fn fire(&mut self) {
+        let reg = self.reg();
+        reg.cnt_value
+            .write(|w| unsafe { w.bits(self.width_cycles) });
+        reg.ctrl.modify(|_, w| w.enable().set_bit());
+    }
+
+    /// Returns [true] while a pulse is active
+    pub fn is_active(&self) -> bool {
+        self.reg().ctrl.read().enable().bit_is_set()
+    }
+
+    /// Stop the timer and release the pin and the timer
+    pub fn release(self) -> (PIN, TIM) {
+        self.reg().ctrl.modify(|_, w| w.enable().clear_bit());
+        (self.pin, self.tim)
+    }
+}
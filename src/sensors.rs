@@ -0,0 +1,69 @@
+//! # Common sensor interfaces of the BSP drivers
+//!
+//! The traits in this module abstract the sensors of the REB1 board, so application code and
+//! telemetry collection can be written once and used with the sensors of derived boards. All
+//! outputs use typed units with integer representations:
+//!
+//! - [TemperatureSensor]: Implemented by the [ADT75 driver](crate::temp_sensor::Adt75TempSensor)
+//!   and returns a [Temperature]
+//! - [VoltageSensor]: Implemented by the [MAX11619 channels](crate::max11619::Max11619Voltages)
+//!   and returns [Millivolts]
+//! - [Accelerometer]: Implemented by the [ADXL343 driver](crate::adxl343::Adxl343) and returns an
+//!   [Acceleration] in [MilliG]
+use core::fmt;
+
+use crate::temp_sensor::Temperature;
+
+/// Voltage in millivolts
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Default, Copy, Clone)]
+pub struct Millivolts(pub i32);
+
+impl fmt::Display for Millivolts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mV", self.0)
+    }
+}
+
+/// Acceleration in thousandths of the standard gravity
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Default, Copy, Clone)]
+pub struct MilliG(pub i32);
+
+impl fmt::Display for MilliG {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mg", self.0)
+    }
+}
+
+/// Acceleration along the three axes of the sensor
+#[derive(PartialEq, Eq, Debug, Default, Copy, Clone)]
+pub struct Acceleration {
+    pub x: MilliG,
+    pub y: MilliG,
+    pub z: MilliG,
+}
+
+/// Sensor which measures a single temperature
+pub trait TemperatureSensor {
+    type Error;
+
+    /// Blocking temperature measurement
+    fn read_temperature(&mut self) -> Result<Temperature, Self::Error>;
+}
+
+/// Sensor with one or more voltage channels
+pub trait VoltageSensor {
+    type Error;
+
+    /// Number of channels. Valid channel numbers range from 0 to the number of channels minus one.
+    fn num_channels(&self) -> u8;
+
+    fn read_voltage(&mut self, channel: u8) -> Result<Millivolts, Self::Error>;
+}
+
+/// Sensor which measures the acceleration along three axes
+pub trait Accelerometer {
+    type Error;
+
+    /// Blocking measurement of all three axes
+    fn read_acceleration(&mut self) -> Result<Acceleration, Self::Error>;
+}
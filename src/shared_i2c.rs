@@ -0,0 +1,155 @@
+//! # Shared I2C bus for multiple devices
+//!
+//! The [I2cBusManager] owns an I2C bus, for example the I2CA [`va108xx_hal::i2c::I2cMaster`] of
+//! the REB1, and hands out [I2cProxy] instances. Each proxy implements the embedded-hal blocking
+//! I2C traits, so the [ADT75 driver](crate::temp_sensor::Adt75TempSensor) and third-party drivers
+//! can be placed on the same bus. Two flavours are provided:
+//!
+//! - [CsI2cBus] locks the bus with a critical section for each transfer. The proxies can be used
+//!   from any context, including interrupt handlers of different priorities.
+//! - [AtomicCheckI2cBus] does not disable interrupts. It is intended for RTIC applications where
+//!   all users of the bus run at the same priority and therefore can not preempt each other. A
+//!   concurrent access from a different priority is detected and causes a panic.
+//!
+//! The bus manager needs to outlive the proxies. It can be created in a `main` function which
+//! never returns, or placed in an RTIC `local` resource of the `init` task to obtain a
+//! `'static` reference.
+//!
+//! ## Examples
+//!
+//! - [Shared I2C bus](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/shared-i2c.rs)
+use core::{
+    cell::{RefCell, UnsafeCell},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use cortex_m::interrupt::Mutex;
+use embedded_hal::blocking::i2c::{AddressMode, Read, Write, WriteRead};
+
+/// Bus manager using a critical section to lock the bus
+pub type CsI2cBus<I2C> = I2cBusManager<Mutex<RefCell<I2C>>>;
+/// Bus manager which panics on concurrent accesses instead of locking the bus
+pub type AtomicCheckI2cBus<I2C> = I2cBusManager<AtomicCheckMutex<I2C>>;
+
+/// Locking mechanism used by the [I2cBusManager]
+pub trait BusMutex {
+    type Bus;
+
+    fn create(bus: Self::Bus) -> Self;
+
+    fn lock<R>(&self, f: impl FnOnce(&mut Self::Bus) -> R) -> R;
+}
+
+impl<T> BusMutex for Mutex<RefCell<T>> {
+    type Bus = T;
+
+    fn create(bus: T) -> Self {
+        Mutex::new(RefCell::new(bus))
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        cortex_m::interrupt::free(|cs| f(&mut self.borrow(cs).borrow_mut()))
+    }
+}
+
+/// Mutex which only checks that the bus is not used concurrently.
+///
+/// Only atomic loads and stores are used, so this also works on the Cortex-M0. On a single core,
+/// a context which preempts the check between the load and the store completes its transfer
+/// before the preempted context continues, so no overlapping access goes undetected.
+pub struct AtomicCheckMutex<T> {
+    busy: AtomicBool,
+    bus: UnsafeCell<T>,
+}
+
+// Safety: Concurrent accesses to the bus are detected and cause a panic
+unsafe impl<T: Send> Sync for AtomicCheckMutex<T> {}
+
+impl<T> BusMutex for AtomicCheckMutex<T> {
+    type Bus = T;
+
+    fn create(bus: T) -> Self {
+        AtomicCheckMutex {
+            busy: AtomicBool::new(false),
+            bus: UnsafeCell::new(bus),
+        }
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        if self.busy.load(Ordering::Acquire) {
+            panic!("concurrent access to the shared I2C bus");
+        }
+        self.busy.store(true, Ordering::Release);
+        // Safety: The busy flag guarantees exclusive access
+        let result = f(unsafe { &mut *self.bus.get() });
+        self.busy.store(false, Ordering::Release);
+        result
+    }
+}
+
+pub struct I2cBusManager<M> {
+    mutex: M,
+}
+
+impl<M: BusMutex> I2cBusManager<M> {
+    pub fn new(bus: M::Bus) -> Self {
+        I2cBusManager {
+            mutex: M::create(bus),
+        }
+    }
+
+    /// Create a new proxy for a device on the bus
+    pub fn acquire_i2c(&self) -> I2cProxy<'_, M> {
+        I2cProxy { mutex: &self.mutex }
+    }
+}
+
+/// Handle to the shared bus which implements the embedded-hal blocking I2C traits
+pub struct I2cProxy<'a, M> {
+    mutex: &'a M,
+}
+
+impl<M> Clone for I2cProxy<'_, M> {
+    fn clone(&self) -> Self {
+        I2cProxy { mutex: self.mutex }
+    }
+}
+
+impl<A: AddressMode, M: BusMutex> Write<A> for I2cProxy<'_, M>
+where
+    M::Bus: Write<A>,
+{
+    type Error = <M::Bus as Write<A>>::Error;
+
+    fn write(&mut self, address: A, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.mutex.lock(|bus| bus.write(address, bytes))
+    }
+}
+
+impl<A: AddressMode, M: BusMutex> Read<A> for I2cProxy<'_, M>
+where
+    M::Bus: Read<A>,
+{
+    type Error = <M::Bus as Read<A>>::Error;
+
+    fn read(&mut self, address: A, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.mutex.lock(|bus| bus.read(address, buffer))
+    }
+}
+
+impl<A: AddressMode, M: BusMutex> WriteRead<A> for I2cProxy<'_, M>
+where
+    M::Bus: WriteRead<A>,
+{
+    type Error = <M::Bus as WriteRead<A>>::Error;
+
+    fn write_read(
+        &mut self,
+        address: A,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.mutex
+            .lock(|bus| bus.write_read(address, bytes, buffer))
+    }
+}
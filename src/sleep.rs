@@ -0,0 +1,105 @@
+//! # Low power sleep with the REB1 button as wakeup source
+//!
+//! [Sleep] puts the core to sleep with the `WFI` instruction. Before entering sleep, all
+//! peripheral and timer clocks which are not explicitly kept are disabled. The clocks are
+//! restored after wakeup, before the interrupt which caused the wakeup is serviced, so interrupt
+//! handlers always see the original clock configuration.
+//!
+//! By default, only the clocks required for GPIO edge interrupts on port A stay enabled, so
+//! the button configured with [Button::edge_irq] can wake up the core.
+//!
+//! ## Examples
+//!
+//! - [Button wakeup from sleep](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/button-wakeup.rs)
+use cortex_m::peripheral::NVIC;
+use va108xx_hal::{clock::PeripheralClocks, pac, timer::ValidTim};
+
+use crate::button::Button;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WakeupError {
+    /// The button was not configured with [Button::edge_irq] or [Button::level_irq]
+    NoButtonIrq,
+    /// The button interrupt is masked in the NVIC, so it can not wake up the core
+    IrqMasked,
+}
+
+/// Sleep configuration which specifies the clocks kept enabled during sleep
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Sleep {
+    peripheral_clks: u32,
+    tim_clks: u32,
+}
+
+impl Default for Sleep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sleep {
+    /// Keep the clocks required for GPIO interrupts on port A enabled and disable everything
+    /// else during sleep.
+    pub const fn new() -> Self {
+        Sleep {
+            peripheral_clks: (1 << PeripheralClocks::PortA as u32)
+                | (1 << PeripheralClocks::Irqsel as u32)
+                | (1 << PeripheralClocks::Ioconfig as u32)
+                | (1 << PeripheralClocks::Gpio as u32),
+            tim_clks: 0,
+        }
+    }
+
+    /// Keep the clock of a peripheral enabled during sleep, for example to wake up with a UART
+    /// interrupt
+    pub fn keep_peripheral_clock(mut self, clock: PeripheralClocks) -> Self {
+        self.peripheral_clks |= 1 << clock as u32;
+        self
+    }
+
+    /// Keep the clock of a timer enabled during sleep, for example to wake up periodically or
+    /// to measure the sleep duration
+    pub fn keep_timer_clock<TIM: ValidTim>(mut self) -> Self {
+        self.tim_clks |= 1 << TIM::TIM_ID;
+        self
+    }
+
+    /// Sleep until any enabled interrupt becomes pending. Returns [true] if the given interrupt
+    /// is pending after wakeup. The interrupt handler runs after this function has restored
+    /// the clocks.
+    pub fn sleep(&self, syscfg: &mut pac::SYSCONFIG, irq: pac::Interrupt) -> bool {
+        cortex_m::interrupt::free(|_| {
+            let peripheral_clks = syscfg.peripheral_clk_enable.read().bits();
+            let tim_clks = syscfg.tim_clk_enable.read().bits();
+            syscfg
+                .peripheral_clk_enable
+                .write(|w| unsafe { w.bits(peripheral_clks & self.peripheral_clks) });
+            syscfg
+                .tim_clk_enable
+                .write(|w| unsafe { w.bits(tim_clks & self.tim_clks) });
+            // Pending interrupts wake up the core even though they are masked by PRIMASK
+            cortex_m::asm::dsb();
+            cortex_m::asm::wfi();
+            syscfg
+                .peripheral_clk_enable
+                .write(|w| unsafe { w.bits(peripheral_clks) });
+            syscfg.tim_clk_enable.write(|w| unsafe { w.bits(tim_clks) });
+            NVIC::is_pending(irq)
+        })
+    }
+
+    /// Sleep until the button interrupt wakes up the core. Wakeups caused by other interrupts
+    /// are serviced and the core goes back to sleep.
+    pub fn sleep_until_button(
+        &self,
+        syscfg: &mut pac::SYSCONFIG,
+        button: &Button,
+    ) -> Result<(), WakeupError> {
+        let irq = button.irq().ok_or(WakeupError::NoButtonIrq)?;
+        if !NVIC::is_enabled(irq) {
+            return Err(WakeupError::IrqMasked);
+        }
+        while !self.sleep(syscfg, irq) {}
+        Ok(())
+    }
+}
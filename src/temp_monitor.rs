@@ -0,0 +1,423 @@
+//! # Temperature statistics and alarm monitoring
+//!
+//! The [TempMonitor] keeps the last `N` timestamped temperature samples in a ring buffer. It
+//! tracks the minimum and maximum temperature since boot, the moving average over the buffer
+//! and the rate of change between the oldest and the newest sample in the buffer. Threshold and
+//! slope alarms are raised and cleared with hysteresis, and every transition is reported as an
+//! [AlarmEvent].
+//!
+//! Samples can be read directly from a [TemperatureSensor] like the
+//! [Adt75TempSensor](crate::temp_sensor::Adt75TempSensor) with [TempMonitor::sample] or fed from
+//! any other source with [TempMonitor::add_sample]. Like the
+//! [Debouncer](crate::debouncer::Debouncer), the monitor only requires millisecond timestamps.
+//!
+//! ## Examples
+//!
+//! - [Temperature monitor](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/temp-monitor.rs)
+use crate::{events::Events, sensors::TemperatureSensor, temp_sensor::Temperature};
+
+/// Timestamped temperature sample
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Sample {
+    pub timestamp_ms: u32,
+    pub temp: Temperature,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Alarm {
+    /// The temperature is above the high threshold
+    High = 0,
+    /// The temperature is below the low threshold
+    Low = 1,
+    /// The temperature rises faster than the slope limit
+    Rising = 2,
+    /// The temperature falls faster than the slope limit
+    Falling = 3,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AlarmEvent {
+    Raised(Alarm),
+    Cleared(Alarm),
+}
+
+/// Alarm configuration of the [TempMonitor]. Alarms which are set to [None] are disabled.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct AlarmConfig {
+    pub high: Option<Temperature>,
+    pub low: Option<Temperature>,
+    /// A threshold alarm is cleared when the temperature is back inside the limits by at least
+    /// this value. This is a temperature difference and not an absolute temperature, for
+    /// example `Temperature::from_raw(16)` is 1 °C. The sign is ignored.
+    pub hysteresis: Temperature,
+    /// Maximum rate of change in millidegrees Celsius per second
+    pub slope_limit_mc_per_s: Option<i32>,
+    /// A slope alarm is cleared when the rate of change is below the limit by at least this
+    /// value. The sign is ignored.
+    pub slope_hysteresis_mc_per_s: i32,
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        AlarmConfig {
+            high: None,
+            low: None,
+            hysteresis: Temperature::from_raw(16),
+            slope_limit_mc_per_s: None,
+            slope_hysteresis_mc_per_s: 0,
+        }
+    }
+}
+
+/// Alarm events generated by a single sample
+pub type AlarmEvents = Events<AlarmEvent, 4>;
+
+pub struct TempMonitor<const N: usize = 32> {
+    cfg: AlarmConfig,
+    samples: [Sample; N],
+    /// Index of the next sample to be written
+    next: usize,
+    len: usize,
+    min: Option<Temperature>,
+    max: Option<Temperature>,
+    /// Bit mask of the active alarms, indexed by [Alarm]
+    active: u8,
+}
+
+impl<const N: usize> TempMonitor<N> {
+    const EMPTY: Sample = Sample {
+        timestamp_ms: 0,
+        temp: Temperature::from_raw(0),
+    };
+
+    pub const fn new(cfg: AlarmConfig) -> Self {
+        TempMonitor {
+            cfg,
+            samples: [Self::EMPTY; N],
+            next: 0,
+            len: 0,
+            min: None,
+            max: None,
+            active: 0,
+        }
+    }
+
+    pub fn config(&self) -> &AlarmConfig {
+        &self.cfg
+    }
+
+    /// Change the alarm configuration. Active alarms are re-evaluated with the next sample.
+    pub fn set_config(&mut self, cfg: AlarmConfig) {
+        self.cfg = cfg;
+    }
+
+    /// Read the temperature from the sensor and add it as a new sample
+    pub fn sample<S: TemperatureSensor>(
+        &mut self,
+        sensor: &mut S,
+        now_ms: u32,
+    ) -> Result<AlarmEvents, S::Error> {
+        let temp = sensor.read_temperature()?;
+        Ok(self.add_sample(Sample {
+            timestamp_ms: now_ms,
+            temp,
+        }))
+    }
+
+    /// Add a sample, update the statistics and evaluate the alarms
+    pub fn add_sample(&mut self, sample: Sample) -> AlarmEvents {
+        if N == 0 {
+            return AlarmEvents::default();
+        }
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        self.min = Some(self.min.map_or(sample.temp, |min| min.min(sample.temp)));
+        self.max = Some(self.max.map_or(sample.temp, |max| max.max(sample.temp)));
+
+        let mut events = AlarmEvents::default();
+        let temp = sample.temp.raw() as i32;
+        let hyst = (self.cfg.hysteresis.raw() as i32).abs();
+        let high = self.cfg.high.map(|high| high.raw() as i32);
+        let low = self.cfg.low.map(|low| low.raw() as i32);
+        self.evaluate(
+            Alarm::High,
+            high.map(|high| (temp > high, temp < high - hyst)),
+            &mut events,
+        );
+        self.evaluate(
+            Alarm::Low,
+            low.map(|low| (temp < low, temp > low + hyst)),
+            &mut events,
+        );
+        let slope = self.cfg.slope_limit_mc_per_s.zip(self.rate_mc_per_s());
+        let slope_hyst = self.cfg.slope_hysteresis_mc_per_s.saturating_abs();
+        self.evaluate(
+            Alarm::Rising,
+            slope.map(|(limit, rate)| (rate > limit, rate < limit.saturating_sub(slope_hyst))),
+            &mut events,
+        );
+        self.evaluate(
+            Alarm::Falling,
+            slope.map(|(limit, rate)| {
+                let limit = limit.saturating_neg();
+                (rate < limit, rate > limit.saturating_add(slope_hyst))
+            }),
+            &mut events,
+        );
+        events
+    }
+
+    /// Update a single alarm. The condition contains the raise and the clear condition, or
+    /// [None] if the alarm is disabled, which clears an active alarm.
+    fn evaluate(&mut self, alarm: Alarm, cond: Option<(bool, bool)>, events: &mut AlarmEvents) {
+        let mask = 1 << alarm as u8;
+        let active = self.active & mask != 0;
+        let (raise, clear) = cond.unwrap_or((false, true));
+        if !active && raise {
+            self.active |= mask;
+            events.push(AlarmEvent::Raised(alarm));
+        } else if active && clear {
+            self.active &= !mask;
+            events.push(AlarmEvent::Cleared(alarm));
+        }
+    }
+
+    pub fn is_active(&self, alarm: Alarm) -> bool {
+        self.active & (1 << alarm as u8) != 0
+    }
+
+    /// Lowest temperature since boot or since the last call to [Self::reset_min_max]
+    pub fn min(&self) -> Option<Temperature> {
+        self.min
+    }
+
+    /// Highest temperature since boot or since the last call to [Self::reset_min_max]
+    pub fn max(&self) -> Option<Temperature> {
+        self.max
+    }
+
+    pub fn reset_min_max(&mut self) {
+        self.min = None;
+        self.max = None;
+    }
+
+    pub fn latest(&self) -> Option<Sample> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(self.samples[(self.next + N - 1) % N])
+    }
+
+    fn oldest(&self) -> Option<Sample> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(self.samples[(self.next + N - self.len) % N])
+    }
+
+    /// Samples in the ring buffer from the oldest to the newest
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> + '_ {
+        (0..self.len).map(move |i| &self.samples[(self.next + N - self.len + i) % N])
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Moving average over the samples in the ring buffer in millidegrees Celsius
+    pub fn average_millicelsius(&self) -> Option<i32> {
+        if self.len == 0 {
+            return None;
+        }
+        let sum: i64 = self.samples().map(|s| s.temp.microcelsius() as i64).sum();
+        Some((sum / self.len as i64 / 1000) as i32)
+    }
+
+    /// Rate of change between the oldest and the newest sample in the ring buffer in
+    /// millidegrees Celsius per second
+    pub fn rate_mc_per_s(&self) -> Option<i32> {
+        let (oldest, latest) = (self.oldest()?, self.latest()?);
+        let dt_ms = latest.timestamp_ms.wrapping_sub(oldest.timestamp_ms);
+        if dt_ms == 0 {
+            return None;
+        }
+        let dtemp_uc = latest.temp.microcelsius() as i64 - oldest.temp.microcelsius() as i64;
+        Some((dtemp_uc / dt_ms as i64) as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp_ms: u32, celsius: i16) -> Sample {
+        Sample {
+            timestamp_ms,
+            temp: Temperature::from_celsius(celsius),
+        }
+    }
+
+    fn collect(events: AlarmEvents) -> [Option<AlarmEvent>; 4] {
+        let mut out = [None; 4];
+        for (slot, event) in out.iter_mut().zip(events) {
+            *slot = Some(event);
+        }
+        out
+    }
+
+    #[test]
+    fn ring_buffer_keeps_last_samples() {
+        let mut monitor = TempMonitor::<3>::new(AlarmConfig::default());
+        assert!(monitor.is_empty());
+        assert_eq!(monitor.latest(), None);
+        for i in 0..5 {
+            assert!(monitor.add_sample(sample(i * 1000, i as i16)).is_empty());
+        }
+        assert_eq!(monitor.len(), 3);
+        let mut temps = [0; 3];
+        for (slot, s) in temps.iter_mut().zip(monitor.samples()) {
+            *slot = s.temp.raw() / 16;
+        }
+        assert_eq!(temps, [2, 3, 4]);
+        assert_eq!(monitor.samples().count(), 3);
+        assert_eq!(monitor.latest(), Some(sample(4000, 4)));
+        // Min and max are tracked since boot, not over the buffer
+        assert_eq!(monitor.min(), Some(Temperature::from_celsius(0)));
+        assert_eq!(monitor.max(), Some(Temperature::from_celsius(4)));
+        monitor.reset_min_max();
+        assert_eq!(monitor.min(), None);
+        monitor.add_sample(sample(5000, -3));
+        assert_eq!(monitor.min(), Some(Temperature::from_celsius(-3)));
+        assert_eq!(monitor.max(), Some(Temperature::from_celsius(-3)));
+    }
+
+    #[test]
+    fn average_over_buffer() {
+        let mut monitor = TempMonitor::<4>::new(AlarmConfig::default());
+        assert_eq!(monitor.average_millicelsius(), None);
+        monitor.add_sample(sample(0, 20));
+        monitor.add_sample(Sample {
+            timestamp_ms: 1000,
+            temp: Temperature::from_raw(20 * 16 + 8),
+        });
+        assert_eq!(monitor.average_millicelsius(), Some(20_250));
+        for i in 2..6 {
+            monitor.add_sample(sample(i * 1000, -10));
+        }
+        assert_eq!(monitor.average_millicelsius(), Some(-10_000));
+    }
+
+    #[test]
+    fn rate_between_oldest_and_newest() {
+        let mut monitor = TempMonitor::<4>::new(AlarmConfig::default());
+        monitor.add_sample(sample(0, 20));
+        // A single sample has no rate
+        assert_eq!(monitor.rate_mc_per_s(), None);
+        monitor.add_sample(sample(2000, 22));
+        assert_eq!(monitor.rate_mc_per_s(), Some(1000));
+        monitor.add_sample(sample(4000, 18));
+        assert_eq!(monitor.rate_mc_per_s(), Some(-500));
+    }
+
+    #[test]
+    fn rate_across_timer_wrap() {
+        let mut monitor = TempMonitor::<2>::new(AlarmConfig::default());
+        monitor.add_sample(sample(u32::MAX - 499, 20));
+        monitor.add_sample(sample(500, 21));
+        assert_eq!(monitor.rate_mc_per_s(), Some(1000));
+    }
+
+    #[test]
+    fn threshold_alarms_with_hysteresis() {
+        let mut monitor = TempMonitor::<4>::new(AlarmConfig {
+            high: Some(Temperature::from_celsius(30)),
+            low: Some(Temperature::from_celsius(0)),
+            hysteresis: Temperature::from_celsius(2),
+            ..Default::default()
+        });
+        assert!(monitor.add_sample(sample(0, 30)).is_empty());
+        assert_eq!(
+            collect(monitor.add_sample(sample(1000, 31))),
+            [Some(AlarmEvent::Raised(Alarm::High)), None, None, None]
+        );
+        assert!(monitor.is_active(Alarm::High));
+        // Inside the hysteresis band
+        assert!(monitor.add_sample(sample(2000, 29)).is_empty());
+        assert!(monitor.add_sample(sample(3000, 28)).is_empty());
+        assert_eq!(
+            collect(monitor.add_sample(sample(4000, 27))),
+            [Some(AlarmEvent::Cleared(Alarm::High)), None, None, None]
+        );
+        assert_eq!(
+            collect(monitor.add_sample(sample(5000, -1))),
+            [Some(AlarmEvent::Raised(Alarm::Low)), None, None, None]
+        );
+        assert!(monitor.add_sample(sample(6000, 2)).is_empty());
+        assert_eq!(
+            collect(monitor.add_sample(sample(7000, 3))),
+            [Some(AlarmEvent::Cleared(Alarm::Low)), None, None, None]
+        );
+    }
+
+    #[test]
+    fn negative_hysteresis_is_treated_as_positive() {
+        let mut monitor = TempMonitor::<4>::new(AlarmConfig {
+            high: Some(Temperature::from_celsius(30)),
+            hysteresis: Temperature::from_celsius(-2),
+            ..Default::default()
+        });
+        monitor.add_sample(sample(0, 31));
+        assert!(monitor.add_sample(sample(1000, 28)).is_empty());
+        assert!(monitor.is_active(Alarm::High));
+    }
+
+    #[test]
+    fn slope_alarms() {
+        let mut monitor = TempMonitor::<2>::new(AlarmConfig {
+            slope_limit_mc_per_s: Some(500),
+            slope_hysteresis_mc_per_s: 100,
+            ..Default::default()
+        });
+        assert!(monitor.add_sample(sample(0, 20)).is_empty());
+        assert_eq!(
+            collect(monitor.add_sample(sample(1000, 21))),
+            [Some(AlarmEvent::Raised(Alarm::Rising)), None, None, None]
+        );
+        // 0.4 °C/s is inside the hysteresis band
+        monitor.add_sample(Sample {
+            timestamp_ms: 2500,
+            temp: Temperature::from_raw(21 * 16 + 10),
+        });
+        assert!(monitor.is_active(Alarm::Rising));
+        assert_eq!(
+            collect(monitor.add_sample(sample(4000, 19))),
+            [
+                Some(AlarmEvent::Cleared(Alarm::Rising)),
+                Some(AlarmEvent::Raised(Alarm::Falling)),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn disabling_an_alarm_clears_it() {
+        let cfg = AlarmConfig {
+            high: Some(Temperature::from_celsius(30)),
+            ..Default::default()
+        };
+        let mut monitor = TempMonitor::<4>::new(cfg);
+        monitor.add_sample(sample(0, 35));
+        assert!(monitor.is_active(Alarm::High));
+        monitor.set_config(AlarmConfig::default());
+        assert_eq!(
+            collect(monitor.add_sample(sample(1000, 35))),
+            [Some(AlarmEvent::Cleared(Alarm::High)), None, None, None]
+        );
+    }
+}
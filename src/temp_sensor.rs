@@ -2,23 +2,73 @@
 //!
 //! [Datasheet](https://www.analog.com/media/en/technical-documentation/data-sheets/ADT75.pdf)
 //!
+//! ## Over-temperature alert
+//!
+//! The sensor compares each conversion against the T_OS and T_HYST setpoints and drives its
+//! open-drain OS/ALERT output accordingly. [Adt75Alert] wraps the GPIO connected to this output
+//! and routes it to an interrupt, so over-temperature does not have to be detected by polling.
+//! With the SMBus alert function enabled, [Adt75TempSensor::alert_source] identifies the
+//! alerting device with an alert response read.
+//!
 //! ## Examples
 //!
 //! - [Temperature Sensor example](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/adt75-temp-sensor.rs)
-use cortex_m::prelude::_embedded_hal_blocking_i2c_Write;
-use embedded_hal::blocking::i2c::{Read, SevenBitAddress};
+use crate::{
+    i2c::{I2cError, RecoverableI2c, RetryPolicy},
+    sensors::TemperatureSensor,
+};
+use embedded_hal::blocking::{
+    delay::DelayMs,
+    i2c::{Read, SevenBitAddress, Write, WriteRead},
+};
 use va108xx_hal::{
+    gpio::{Input, InputConfig, InterruptEdge, Pin, PinId},
     i2c::{Error, I2cMaster, I2cSpeed, MasterConfig},
-    pac::{I2CA, SYSCONFIG},
+    pac::{self, I2CA, SYSCONFIG},
+    prelude::*,
     time::Hertz,
+    utility::IrqCfg,
 };
 
-const ADT75_I2C_ADDR: u8 = 0b1001000;
+/// Address with all address pins tied low
+const ADT75_I2C_BASE_ADDR: u8 = 0b1001000;
+/// SMBus alert response address
+const SMBUS_ARA: u8 = 0b0001100;
+/// Conversion time of a one-shot measurement
+pub const ONE_SHOT_CONVERSION_MS: u32 = 60;
+
+/// ADT75 sensor on the REB1 board, connected to I2CA with all address pins tied low
+pub type OnBoardAdt75 = Adt75TempSensor<I2cMaster<I2CA, SevenBitAddress>>;
+/// [OnBoardAdt75] on a [RecoverableI2c] bus
+pub type RecoverableOnBoardAdt75 = Adt75TempSensor<RecoverableI2c<I2CA>>;
+
+/// Levels of the A2 to A0 address pins of the ADT75
+#[derive(PartialEq, Eq, Debug, Default, Copy, Clone)]
+pub struct AddressPins {
+    pub a2: bool,
+    pub a1: bool,
+    pub a0: bool,
+}
+
+impl AddressPins {
+    pub const fn new(a2: bool, a1: bool, a0: bool) -> Self {
+        AddressPins { a2, a1, a0 }
+    }
 
-pub struct Adt75TempSensor {
-    sensor_if: I2cMaster<I2CA, SevenBitAddress>,
+    /// Seven bit I2C address selected by the address pins
+    pub const fn address(&self) -> u8 {
+        ADT75_I2C_BASE_ADDR | ((self.a2 as u8) << 2) | ((self.a1 as u8) << 1) | self.a0 as u8
+    }
+}
+
+pub struct Adt75TempSensor<I2C> {
+    sensor_if: I2C,
+    addr: u8,
     cmd_buf: [u8; 1],
-    current_reg: RegAddresses,
+    /// Address pointer of the sensor, or [None] if it is unknown after a failed transfer
+    current_reg: Option<RegAddresses>,
+    /// Start time of a pending one-shot conversion
+    one_shot_start: Option<u32>,
 }
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
@@ -30,51 +80,706 @@ pub enum RegAddresses {
     OneShot = 0x04,
 }
 
-impl Adt75TempSensor {
-    pub fn new(
+/// Temperature in the fixed-point format of the ADT75 with a resolution of 1/16 °C. The
+/// conversions only use integer arithmetic, except for [Temperature::as_celsius_f32].
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Default, Copy, Clone)]
+pub struct Temperature(i16);
+
+impl Temperature {
+    /// Lowest temperature which can be represented in the 12-bit format
+    pub const MIN: Temperature = Temperature(-2048);
+    /// Highest temperature which can be represented in the 12-bit format
+    pub const MAX: Temperature = Temperature(2047);
+
+    /// Create a temperature from a value in 1/16 °C. The value is clamped to the 12-bit range.
+    pub const fn from_raw(sixteenths: i16) -> Self {
+        if sixteenths < Self::MIN.0 {
+            Self::MIN
+        } else if sixteenths > Self::MAX.0 {
+            Self::MAX
+        } else {
+            Temperature(sixteenths)
+        }
+    }
+
+    pub const fn from_celsius(celsius: i16) -> Self {
+        Self::from_raw(celsius.saturating_mul(16))
+    }
+
+    /// Create a temperature from millidegrees Celsius, rounded to the nearest 1/16 °C
+    pub const fn from_millicelsius(millicelsius: i32) -> Self {
+        let scaled = millicelsius as i64 * 2;
+        let rounded = if scaled < 0 {
+            (scaled - 62) / 125
+        } else {
+            (scaled + 62) / 125
+        };
+        if rounded < Self::MIN.0 as i64 {
+            Self::MIN
+        } else if rounded > Self::MAX.0 as i64 {
+            Self::MAX
+        } else {
+            Temperature(rounded as i16)
+        }
+    }
+
+    /// Decode the left-justified 12-bit two's complement value of the temperature and setpoint
+    /// registers
+    pub const fn from_register(value: u16) -> Self {
+        Temperature((value as i16) >> 4)
+    }
+
+    pub const fn to_register(self) -> u16 {
+        (self.0 << 4) as u16
+    }
+
+    /// Temperature in 1/16 °C
+    pub const fn raw(self) -> i16 {
+        self.0
+    }
+
+    /// Temperature in microdegrees Celsius. This conversion is lossless.
+    pub const fn microcelsius(self) -> i32 {
+        self.0 as i32 * 62_500
+    }
+
+    /// Temperature in millidegrees Celsius. One LSB is 62.5 m°C, so odd raw values are rounded
+    /// away from zero.
+    pub const fn millicelsius(self) -> i32 {
+        let doubled = self.0 as i32 * 125;
+        if doubled < 0 {
+            (doubled - 1) / 2
+        } else {
+            (doubled + 1) / 2
+        }
+    }
+
+    /// Temperature in degrees Celsius as floating point number. This requires soft-float
+    /// routines on the Cortex-M0.
+    pub fn as_celsius_f32(self) -> f32 {
+        self.0 as f32 / 16.0
+    }
+}
+
+impl core::fmt::Display for Temperature {
+    /// Formats the exact value with four decimal places, without floating point arithmetic
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let abs = self.0.unsigned_abs();
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{}{}.{:04}", sign, abs / 16, (abs % 16) * 625)
+    }
+}
+
+/// Number of consecutive faults required to trigger the OS/ALERT output
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum FaultQueue {
+    One = 0b00,
+    Two = 0b01,
+    Four = 0b10,
+    Six = 0b11,
+}
+
+/// Behaviour of the OS/ALERT output
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum OsMode {
+    /// The output stays active until the temperature drops below T_HYST
+    Comparator = 0,
+    /// The output stays active until a register of the sensor is read
+    Interrupt = 1,
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum OsPolarity {
+    ActiveLow = 0,
+    ActiveHigh = 1,
+}
+
+/// Configuration register of the ADT75. The default value corresponds to the power-on state.
+#[derive(PartialEq, Eq, Debug, Default, Copy, Clone)]
+pub struct Config(u8);
+
+impl Config {
+    const SHUTDOWN: u8 = 1 << 0;
+    const OS_MODE: u8 = 1 << 1;
+    const OS_POLARITY: u8 = 1 << 2;
+    const FAULT_QUEUE_SHIFT: u8 = 3;
+    const FAULT_QUEUE: u8 = 0b11 << Self::FAULT_QUEUE_SHIFT;
+    const ONE_SHOT: u8 = 1 << 5;
+    const SMBUS_ALERT: u8 = 1 << 7;
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Config(bits)
+    }
+
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    fn set_bit(&mut self, mask: u8, set: bool) -> &mut Self {
+        if set {
+            self.0 |= mask;
+        } else {
+            self.0 &= !mask;
+        }
+        self
+    }
+
+    pub fn shutdown(&self) -> bool {
+        self.0 & Self::SHUTDOWN != 0
+    }
+
+    /// Shutdown mode. All circuitry except the I2C interface is powered down.
+    pub fn set_shutdown(&mut self, shutdown: bool) -> &mut Self {
+        self.set_bit(Self::SHUTDOWN, shutdown)
+    }
+
+    pub fn os_mode(&self) -> OsMode {
+        if self.0 & Self::OS_MODE != 0 {
+            OsMode::Interrupt
+        } else {
+            OsMode::Comparator
+        }
+    }
+
+    pub fn set_os_mode(&mut self, mode: OsMode) -> &mut Self {
+        self.set_bit(Self::OS_MODE, mode == OsMode::Interrupt)
+    }
+
+    pub fn os_polarity(&self) -> OsPolarity {
+        if self.0 & Self::OS_POLARITY != 0 {
+            OsPolarity::ActiveHigh
+        } else {
+            OsPolarity::ActiveLow
+        }
+    }
+
+    pub fn set_os_polarity(&mut self, polarity: OsPolarity) -> &mut Self {
+        self.set_bit(Self::OS_POLARITY, polarity == OsPolarity::ActiveHigh)
+    }
+
+    pub fn fault_queue(&self) -> FaultQueue {
+        match (self.0 & Self::FAULT_QUEUE) >> Self::FAULT_QUEUE_SHIFT {
+            0b00 => FaultQueue::One,
+            0b01 => FaultQueue::Two,
+            0b10 => FaultQueue::Four,
+            _ => FaultQueue::Six,
+        }
+    }
+
+    pub fn set_fault_queue(&mut self, queue: FaultQueue) -> &mut Self {
+        self.0 = (self.0 & !Self::FAULT_QUEUE) | ((queue as u8) << Self::FAULT_QUEUE_SHIFT);
+        self
+    }
+
+    pub fn one_shot(&self) -> bool {
+        self.0 & Self::ONE_SHOT != 0
+    }
+
+    /// One-shot mode. The sensor only performs a conversion when the one-shot register is written
+    /// and powers down automatically after the conversion. The shutdown bit needs to be cleared
+    /// in this mode.
+    pub fn set_one_shot(&mut self, one_shot: bool) -> &mut Self {
+        self.set_bit(Self::ONE_SHOT, one_shot)
+    }
+
+    pub fn smbus_alert(&self) -> bool {
+        self.0 & Self::SMBUS_ALERT != 0
+    }
+
+    /// Enable the SMBus alert function of the OS/ALERT pin
+    pub fn set_smbus_alert(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(Self::SMBUS_ALERT, enable)
+    }
+}
+
+/// GPIO connected to the OS/ALERT output of the ADT75. The output is open-drain, so the pin
+/// should be configured with a pull-up if there is no external one.
+pub struct Adt75Alert<I: PinId, C: InputConfig> {
+    pin: Pin<I, Input<C>>,
+    polarity: OsPolarity,
+}
+
+impl<I: PinId, C: InputConfig> Adt75Alert<I, C> {
+    /// The polarity needs to match the polarity configured in the sensor with
+    /// [Adt75TempSensor::set_os_polarity]
+    pub fn new(pin: Pin<I, Input<C>>, polarity: OsPolarity) -> Self {
+        Adt75Alert { pin, polarity }
+    }
+
+    /// Configures an IRQ when the output becomes active. In comparator mode, the output
+    /// becomes inactive again when the temperature drops below T_HYST.
+    pub fn edge_irq(
+        mut self,
+        irq_cfg: IrqCfg,
+        syscfg: Option<&mut pac::SYSCONFIG>,
+        irqsel: Option<&mut pac::IRQSEL>,
+    ) -> Self {
+        let edge = match self.polarity {
+            OsPolarity::ActiveLow => InterruptEdge::HighToLow,
+            OsPolarity::ActiveHigh => InterruptEdge::LowToHigh,
+        };
+        self.pin = self.pin.interrupt_edge(edge, irq_cfg, syscfg, irqsel);
+        self
+    }
+
+    /// Returns [true] if the OS/ALERT output is active
+    pub fn is_active(&self) -> bool {
+        let high = self.pin.is_high().ok().unwrap();
+        high == (self.polarity == OsPolarity::ActiveHigh)
+    }
+
+    pub fn release(self) -> Pin<I, Input<C>> {
+        self.pin
+    }
+}
+
+impl OnBoardAdt75 {
+    /// Create the driver for the on-board sensor. I2CA is configured for 100 kHz.
+    pub fn new_on_board(
         i2ca: I2CA,
         sys_clk: impl Into<Hertz> + Copy,
         sys_cfg: Option<&mut SYSCONFIG>,
     ) -> Result<Self, Error> {
-        let mut sensor = Adt75TempSensor {
-            sensor_if: I2cMaster::i2ca(
+        Self::new(
+            I2cMaster::i2ca(
                 i2ca,
                 MasterConfig::default(),
                 sys_clk,
                 I2cSpeed::Regular100khz,
                 sys_cfg,
             ),
+            AddressPins::default(),
+        )
+    }
+}
+
+impl RecoverableOnBoardAdt75 {
+    /// Create the driver for the on-board sensor on a [RecoverableI2c] bus with the given retry
+    /// policy. I2CA is configured for 100 kHz. The I2CA lines are not connected to any GPIO
+    /// pins on the REB1 board, so a bus recovery resets the I2C master but can not clock out
+    /// the sensor, see the [i2c module](crate::i2c#bus-pins).
+    pub fn new_on_board_recoverable(
+        i2ca: I2CA,
+        sys_clk: impl Into<Hertz> + Copy,
+        sys_cfg: Option<&mut SYSCONFIG>,
+        policy: RetryPolicy,
+    ) -> Result<Self, I2cError<Error>> {
+        let i2c = RecoverableI2c::i2ca(i2ca, sys_clk, I2cSpeed::Regular100khz, sys_cfg)
+            .with_policy(policy);
+        Self::new(i2c, AddressPins::default())
+    }
+}
+
+impl<I2C, E> Adt75TempSensor<I2C>
+where
+    I2C: Write<SevenBitAddress, Error = E>
+        + Read<SevenBitAddress, Error = E>
+        + WriteRead<SevenBitAddress, Error = E>,
+{
+    /// Create a driver for a sensor on any I2C bus. The address pointer of the sensor is reset
+    /// to the temperature register.
+    pub fn new(i2c: I2C, addr_pins: AddressPins) -> Result<Self, E> {
+        let mut sensor = Adt75TempSensor {
+            sensor_if: i2c,
+            addr: addr_pins.address(),
             cmd_buf: [RegAddresses::Temperature as u8],
-            current_reg: RegAddresses::Temperature,
+            current_reg: Some(RegAddresses::Temperature),
+            one_shot_start: None,
         };
-        sensor.select_reg(RegAddresses::Temperature)?;
+        sensor
+            .sensor_if
+            .write(sensor.addr, &[RegAddresses::Temperature as u8])?;
         Ok(sensor)
     }
 
-    pub fn select_reg(&mut self, reg: RegAddresses) -> Result<(), Error> {
-        if reg != self.current_reg {
+    /// Seven bit I2C address of the sensor
+    pub fn address(&self) -> u8 {
+        self.addr
+    }
+
+    /// Release the I2C bus
+    pub fn release(self) -> I2C {
+        self.sensor_if
+    }
+
+    /// Update the cached address pointer after a transfer which sets it. A failed transfer may
+    /// or may not have reached the sensor, so the pointer is unknown afterwards and is selected
+    /// again by the next temperature read.
+    fn track_reg<T>(&mut self, reg: RegAddresses, result: Result<T, E>) -> Result<T, E> {
+        self.current_reg = result.is_ok().then_some(reg);
+        result
+    }
+
+    pub fn select_reg(&mut self, reg: RegAddresses) -> Result<(), E> {
+        if self.current_reg != Some(reg) {
             self.cmd_buf[0] = reg as u8;
-            self.current_reg = reg;
-            self.sensor_if.write(ADT75_I2C_ADDR, &self.cmd_buf[0..1])?;
+            let result = self.sensor_if.write(self.addr, &self.cmd_buf[0..1]);
+            self.track_reg(reg, result)?;
         }
         Ok(())
     }
 
-    pub fn read_temperature(&mut self) -> Result<f32, Error> {
-        if self.current_reg != RegAddresses::Temperature {
-            self.select_reg(RegAddresses::Temperature)?;
+    fn read_reg_u8(&mut self, reg: RegAddresses) -> Result<u8, E> {
+        let mut reply: [u8; 1] = [0; 1];
+        let result = self
+            .sensor_if
+            .write_read(self.addr, &[reg as u8], &mut reply);
+        self.track_reg(reg, result)?;
+        Ok(reply[0])
+    }
+
+    fn write_reg_u8(&mut self, reg: RegAddresses, value: u8) -> Result<(), E> {
+        // Writing a register also sets the address pointer
+        let result = self.sensor_if.write(self.addr, &[reg as u8, value]);
+        self.track_reg(reg, result)
+    }
+
+    pub fn read_config(&mut self) -> Result<Config, E> {
+        Ok(Config::from_bits(
+            self.read_reg_u8(RegAddresses::Configuration)?,
+        ))
+    }
+
+    pub fn write_config(&mut self, config: Config) -> Result<(), E> {
+        self.write_reg_u8(RegAddresses::Configuration, config.bits())
+    }
+
+    /// Read-modify-write of the configuration register. Returns the written configuration.
+    pub fn modify_config(&mut self, f: impl FnOnce(&mut Config)) -> Result<Config, E> {
+        let mut config = self.read_config()?;
+        f(&mut config);
+        self.write_config(config)?;
+        Ok(config)
+    }
+
+    pub fn set_shutdown(&mut self, shutdown: bool) -> Result<(), E> {
+        self.modify_config(|cfg| {
+            cfg.set_shutdown(shutdown);
+        })
+        .map(|_| ())
+    }
+
+    pub fn set_os_mode(&mut self, mode: OsMode) -> Result<(), E> {
+        self.modify_config(|cfg| {
+            cfg.set_os_mode(mode);
+        })
+        .map(|_| ())
+    }
+
+    pub fn set_os_polarity(&mut self, polarity: OsPolarity) -> Result<(), E> {
+        self.modify_config(|cfg| {
+            cfg.set_os_polarity(polarity);
+        })
+        .map(|_| ())
+    }
+
+    pub fn set_fault_queue(&mut self, queue: FaultQueue) -> Result<(), E> {
+        self.modify_config(|cfg| {
+            cfg.set_fault_queue(queue);
+        })
+        .map(|_| ())
+    }
+
+    pub fn set_one_shot_mode(&mut self, one_shot: bool) -> Result<(), E> {
+        self.modify_config(|cfg| {
+            cfg.set_one_shot(one_shot);
+        })
+        .map(|_| ())
+    }
+
+    pub fn set_smbus_alert(&mut self, enable: bool) -> Result<(), E> {
+        self.modify_config(|cfg| {
+            cfg.set_smbus_alert(enable);
+        })
+        .map(|_| ())
+    }
+
+    fn read_reg_u16(&mut self, reg: RegAddresses) -> Result<u16, E> {
+        let mut reply: [u8; 2] = [0; 2];
+        let result = self
+            .sensor_if
+            .write_read(self.addr, &[reg as u8], &mut reply);
+        self.track_reg(reg, result)?;
+        Ok(u16::from_be_bytes(reply))
+    }
+
+    fn write_reg_u16(&mut self, reg: RegAddresses, value: u16) -> Result<(), E> {
+        let value = value.to_be_bytes();
+        let result = self
+            .sensor_if
+            .write(self.addr, &[reg as u8, value[0], value[1]]);
+        self.track_reg(reg, result)
+    }
+
+    /// Over-temperature setpoint
+    pub fn t_os(&mut self) -> Result<Temperature, E> {
+        Ok(Temperature::from_register(
+            self.read_reg_u16(RegAddresses::TOsSetPoint)?,
+        ))
+    }
+
+    /// Set the over-temperature setpoint. The OS/ALERT output becomes
+    /// active when the temperature exceeds this value.
+    pub fn set_t_os(&mut self, temp: Temperature) -> Result<(), E> {
+        self.write_reg_u16(RegAddresses::TOsSetPoint, temp.to_register())
+    }
+
+    /// Hysteresis setpoint
+    pub fn t_hyst(&mut self) -> Result<Temperature, E> {
+        Ok(Temperature::from_register(
+            self.read_reg_u16(RegAddresses::THystSetpoint)?,
+        ))
+    }
+
+    /// Set the hysteresis setpoint. In comparator mode, the OS/ALERT output
+    /// becomes inactive when the temperature drops below this value.
+    pub fn set_t_hyst(&mut self, temp: Temperature) -> Result<(), E> {
+        self.write_reg_u16(RegAddresses::THystSetpoint, temp.to_register())
+    }
+
+    /// Perform an SMBus alert response read and return the address of the alerting device. The
+    /// read fails with a NACK if no device on the bus asserts its SMBus alert.
+    pub fn alert_response(&mut self) -> Result<u8, E> {
+        let mut reply: [u8; 1] = [0; 1];
+        self.sensor_if.read(SMBUS_ARA, &mut reply)?;
+        Ok(reply[0] >> 1)
+    }
+
+    /// Returns [true] if this sensor answers the SMBus alert response read. In SMBus alert mode,
+    /// this also clears the alert of the sensor.
+    pub fn alert_source(&mut self) -> Result<bool, E> {
+        Ok(self.alert_response()? == self.addr)
+    }
+
+    /// Put the sensor into the one-shot mode. The sensor only performs a conversion when it is
+    /// triggered with [Self::start_one_shot] and powers itself down again after each conversion,
+    /// so it draws the shutdown current between the conversions.
+    ///
+    /// Please note that the shutdown bit of the configuration register is cleared, unlike the
+    /// workflow of putting the sensor into shutdown first: The power-down between conversions is
+    /// handled by the one-shot mode itself, while the shutdown mode would keep the sensor powered
+    /// down when a conversion is triggered.
+    pub fn enter_one_shot_mode(&mut self) -> Result<(), E> {
+        self.modify_config(|cfg| {
+            cfg.set_one_shot(true).set_shutdown(false);
+        })
+        .map(|_| ())
+    }
+
+    /// Return to the continuous conversion mode
+    pub fn enter_continuous_mode(&mut self) -> Result<(), E> {
+        self.one_shot_start = None;
+        self.modify_config(|cfg| {
+            cfg.set_one_shot(false).set_shutdown(false);
+        })
+        .map(|_| ())
+    }
+
+    /// Trigger a single conversion in one-shot mode. The current time in milliseconds is used
+    /// by [Self::poll_one_shot] to determine when the conversion is complete.
+    pub fn start_one_shot(&mut self, now_ms: u32) -> Result<(), E> {
+        // Writing the address of the one-shot register triggers the conversion
+        self.cmd_buf[0] = RegAddresses::OneShot as u8;
+        let result = self.sensor_if.write(self.addr, &self.cmd_buf[0..1]);
+        self.track_reg(RegAddresses::OneShot, result)?;
+        self.one_shot_start = Some(now_ms);
+        Ok(())
+    }
+
+    /// Returns the result of the conversion started with [Self::start_one_shot] once the
+    /// conversion time has passed. If no conversion was started, the last converted value is
+    /// read.
+    pub fn poll_one_shot(&mut self, now_ms: u32) -> nb::Result<Temperature, E> {
+        if let Some(start) = self.one_shot_start {
+            if now_ms.wrapping_sub(start) < ONE_SHOT_CONVERSION_MS {
+                return Err(nb::Error::WouldBlock);
+            }
         }
+        self.one_shot_start = None;
+        self.read_temperature().map_err(nb::Error::Other)
+    }
+
+    /// Trigger a conversion in one-shot mode and wait for the result
+    pub fn read_one_shot(&mut self, delay: &mut impl DelayMs<u32>) -> Result<Temperature, E> {
+        self.start_one_shot(0)?;
+        delay.delay_ms(ONE_SHOT_CONVERSION_MS);
+        self.one_shot_start = None;
+        self.read_temperature()
+    }
+
+    pub fn read_temperature(&mut self) -> Result<Temperature, E> {
+        self.select_reg(RegAddresses::Temperature)?;
         let mut reply: [u8; 2] = [0; 2];
-        self.sensor_if.read(ADT75_I2C_ADDR, &mut reply)?;
-        let adc_code = (((reply[0] as u16) << 8) | reply[1] as u16) >> 4;
-        let temp_celcius: f32 = if ((adc_code >> 11) & 0x01) == 0 {
-            // Sign bit not set, positiv value
-            // Divide ADC code by 16 according to datasheet
-            adc_code as f32 / 16.0
-        } else {
-            // Calculation for negative values, assuming all 12 bits are used
-            (adc_code - 4096) as f32 / 16.0
+        self.sensor_if.read(self.addr, &mut reply)?;
+        Ok(Temperature::from_register(u16::from_be_bytes(reply)))
+    }
+}
+
+impl<I2C, E> TemperatureSensor for Adt75TempSensor<I2C>
+where
+    I2C: Write<SevenBitAddress, Error = E>
+        + Read<SevenBitAddress, Error = E>
+        + WriteRead<SevenBitAddress, Error = E>,
+{
+    type Error = E;
+
+    fn read_temperature(&mut self) -> Result<Temperature, E> {
+        Adt75TempSensor::read_temperature(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Temperature data format table of the datasheet: (temperature in °C, 12-bit code)
+    const DATASHEET_VECTORS: [(i32, u16); 10] = [
+        (-55, 0b1100_1001_0000),
+        (-50, 0b1100_1110_0000),
+        (-25, 0b1110_0111_0000),
+        (0, 0b0000_0000_0000),
+        (10, 0b0000_1010_0000),
+        (25, 0b0001_1001_0000),
+        (50, 0b0011_0010_0000),
+        (75, 0b0100_1011_0000),
+        (100, 0b0110_0100_0000),
+        (125, 0b0111_1101_0000),
+    ];
+
+    #[test]
+    fn datasheet_vectors() {
+        for (celsius, code) in DATASHEET_VECTORS {
+            let temp = Temperature::from_register(code << 4);
+            assert_eq!(temp.millicelsius(), celsius * 1000, "code {:#05x}", code);
+            assert_eq!(temp.to_register(), code << 4);
+            assert_eq!(temp, Temperature::from_celsius(celsius as i16));
+        }
+    }
+
+    #[test]
+    fn sixteenth_degree_steps() {
+        let minus_lsb = Temperature::from_register(0xfff << 4);
+        assert_eq!(minus_lsb.raw(), -1);
+        assert_eq!(minus_lsb.microcelsius(), -62_500);
+        assert_eq!(minus_lsb.millicelsius(), -63);
+        let plus_lsb = Temperature::from_register(0x001 << 4);
+        assert_eq!(plus_lsb.microcelsius(), 62_500);
+        assert_eq!(plus_lsb.millicelsius(), 63);
+        assert_eq!(Temperature::from_raw(-8).millicelsius(), -500);
+    }
+
+    #[test]
+    fn unused_low_bits_are_ignored() {
+        assert_eq!(
+            Temperature::from_register((0xc90 << 4) | 0xf).raw(),
+            -55 * 16
+        );
+    }
+
+    #[test]
+    fn full_range_round_trip() {
+        for raw in -55 * 16..=125 * 16 {
+            let temp = Temperature::from_raw(raw);
+            assert_eq!(Temperature::from_register(temp.to_register()), temp);
+            assert_eq!(Temperature::from_millicelsius(temp.millicelsius()), temp);
+            assert_eq!(temp.microcelsius(), raw as i32 * 62_500);
+            assert_eq!(temp.as_celsius_f32(), raw as f32 / 16.0);
+        }
+    }
+
+    #[test]
+    fn millicelsius_saturates() {
+        assert_eq!(Temperature::from_millicelsius(200_000), Temperature::MAX);
+        assert_eq!(Temperature::from_millicelsius(-200_000), Temperature::MIN);
+        assert_eq!(Temperature::from_millicelsius(i32::MAX), Temperature::MAX);
+        assert_eq!(Temperature::from_millicelsius(i32::MIN), Temperature::MIN);
+    }
+
+    #[test]
+    fn display() {
+        extern crate std;
+        use std::string::ToString;
+        assert_eq!(Temperature::from_raw(25 * 16 + 1).to_string(), "25.0625");
+        assert_eq!(Temperature::from_raw(-1).to_string(), "-0.0625");
+        assert_eq!(Temperature::from_celsius(-55).to_string(), "-55.0000");
+    }
+
+    /// Simulated ADT75 on the bus. Every register holds a distinct value, and the next transfer
+    /// can be made to fail before or after the address pointer was written.
+    struct FakeAdt75 {
+        pointer: u8,
+        fail_next: bool,
+        fail_before_pointer: bool,
+    }
+
+    impl FakeAdt75 {
+        const TEMPERATURE: u16 = 0x1900;
+
+        fn register(&self) -> [u8; 2] {
+            match self.pointer {
+                0x00 => Self::TEMPERATURE.to_be_bytes(),
+                reg => [reg, reg],
+            }
+        }
+
+        fn set_pointer(&mut self, bytes: &[u8]) -> Result<(), ()> {
+            if !(self.fail_next && self.fail_before_pointer) {
+                self.pointer = bytes[0];
+            }
+            if self.fail_next {
+                self.fail_next = false;
+                return Err(());
+            }
+            Ok(())
+        }
+    }
+
+    impl Write<SevenBitAddress> for FakeAdt75 {
+        type Error = ();
+
+        fn write(&mut self, _addr: u8, bytes: &[u8]) -> Result<(), ()> {
+            self.set_pointer(bytes)
+        }
+    }
+
+    impl Read<SevenBitAddress> for FakeAdt75 {
+        type Error = ();
+
+        fn read(&mut self, _addr: u8, buffer: &mut [u8]) -> Result<(), ()> {
+            buffer.copy_from_slice(&self.register()[..buffer.len()]);
+            Ok(())
+        }
+    }
+
+    impl WriteRead<SevenBitAddress> for FakeAdt75 {
+        type Error = ();
+
+        fn write_read(&mut self, _addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+            self.set_pointer(bytes)?;
+            self.read(0, buffer)
+        }
+    }
+
+    #[test]
+    fn failed_transfer_invalidates_the_pointer() {
+        let fake = FakeAdt75 {
+            pointer: 0x00,
+            fail_next: false,
+            fail_before_pointer: false,
         };
-        Ok(temp_celcius)
+        let mut sensor = Adt75TempSensor::new(fake, AddressPins::default()).unwrap();
+        let expected = Temperature::from_register(FakeAdt75::TEMPERATURE);
+        assert_eq!(sensor.read_temperature(), Ok(expected));
+        // The pointer moves to the configuration register, but the transfer fails
+        sensor.sensor_if.fail_next = true;
+        assert_eq!(sensor.read_config(), Err(()));
+        assert_eq!(sensor.read_temperature(), Ok(expected));
+        // A select of the temperature register which does not reach the sensor is repeated by
+        // the next read
+        sensor.t_os().unwrap();
+        sensor.sensor_if.fail_next = true;
+        sensor.sensor_if.fail_before_pointer = true;
+        assert_eq!(sensor.read_temperature(), Err(()));
+        assert_eq!(sensor.read_temperature(), Ok(expected));
     }
 }
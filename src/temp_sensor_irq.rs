@@ -0,0 +1,225 @@
+//! # Interrupt-driven temperature reads of the on-board ADT75
+//!
+//! The blocking [Adt75TempSensor::read_temperature](crate::temp_sensor::Adt75TempSensor::read_temperature)
+//! keeps the CPU busy for the whole I2C transfer, which takes roughly half a millisecond at
+//! 100 kHz. [Adt75IrqReader] takes over the [OnBoardAdt75] driver and runs the register select
+//! write and the 2-byte temperature read as a state machine in the I2CA interrupt handler.
+//!
+//! A read is started with [Adt75IrqReader::start], for example from a periodic timer interrupt.
+//! The I2CA interrupt handler calls [Adt75IrqReader::on_interrupt], which returns [true] once
+//! the transfer is complete. The result can then be fetched with the non-blocking
+//! [Adt75IrqReader::poll]. The reader is usually placed in an RTIC shared resource or in a
+//! [cortex_m::interrupt::Mutex] so both interrupt handlers and the main loop can access it.
+//!
+//! ## Examples
+//!
+//! - [Interrupt-driven temperature reads with RTIC](https://egit.irs.uni-stuttgart.de/rust/vorago-reb1/src/branch/main/examples/adt75-irq-rtic.rs)
+use cortex_m::peripheral::NVIC;
+use va108xx_hal::{
+    clock::{enable_peripheral_clock, PeripheralClocks},
+    i2c::Error,
+    pac::{self, i2ca, I2CA, IRQSEL, SYSCONFIG},
+    utility::IrqCfg,
+};
+
+use crate::{
+    i2c::I2cError,
+    temp_sensor::{OnBoardAdt75, RegAddresses, Temperature},
+};
+
+/// Command register value for a transfer with START and STOP condition
+const I2C_CMD_START_WITH_STOP: u32 = 0b11;
+/// Command register value which cancels the current transfer
+const I2C_CMD_CANCEL: u32 = 0b100;
+/// Direction bit of the address register for read transfers
+const I2C_DIR_READ: u32 = 1;
+/// Index of I2CA in the IRQSEL I2C master registers
+const I2CA_IRQSEL_IDX: usize = 0;
+
+/// A read was started while the previous transfer was still in progress
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Busy;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum State {
+    Idle,
+    /// Writing the address of the temperature register
+    SelectReg,
+    /// Reading the two bytes of the temperature register
+    ReadTemp,
+}
+
+pub struct Adt75IrqReader {
+    sensor: OnBoardAdt75,
+    irq: pac::Interrupt,
+    state: State,
+    result: Option<Result<Temperature, I2cError<Error>>>,
+}
+
+impl Adt75IrqReader {
+    /// Route the I2CA master interrupt to the vector specified in the IRQ configuration. The
+    /// interrupt is only unmasked in the NVIC if [IrqCfg::enable] is set.
+    pub fn new(
+        sensor: OnBoardAdt75,
+        irq_cfg: IrqCfg,
+        sys_cfg: Option<&mut SYSCONFIG>,
+        irqsel: Option<&mut IRQSEL>,
+    ) -> Self {
+        if let Some(sys_cfg) = sys_cfg {
+            enable_peripheral_clock(sys_cfg, PeripheralClocks::Irqsel);
+        }
+        if irq_cfg.route {
+            if let Some(irqsel) = irqsel {
+                irqsel.i2c_ms[I2CA_IRQSEL_IDX].write(|w| unsafe { w.bits(irq_cfg.irq as u32) });
+            }
+        }
+        let reg = regs();
+        reg.irq_enb.write(|w| unsafe { w.bits(0) });
+        reg.irq_clr.write(|w| unsafe { w.bits(u32::MAX) });
+        if irq_cfg.enable {
+            unsafe {
+                NVIC::unmask(irq_cfg.irq);
+            }
+        }
+        Adt75IrqReader {
+            sensor,
+            irq: irq_cfg.irq,
+            state: State::Idle,
+            result: None,
+        }
+    }
+
+    /// Interrupt vector of the I2CA master interrupt
+    pub fn irq(&self) -> pac::Interrupt {
+        self.irq
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.state != State::Idle
+    }
+
+    /// Start reading the temperature. A result which was not fetched with [Self::poll] yet is
+    /// discarded.
+    pub fn start(&mut self) -> Result<(), Busy> {
+        if self.is_busy() {
+            return Err(Busy);
+        }
+        self.result = None;
+        let reg = regs();
+        reg.fifo_clr
+            .write(|w| w.rxfifo().set_bit().txfifo().set_bit());
+        reg.words.write(|w| unsafe { w.bits(1) });
+        reg.data
+            .write(|w| unsafe { w.bits(RegAddresses::Temperature as u32) });
+        reg.address
+            .write(|w| unsafe { w.bits((self.sensor.address() as u32) << 1) });
+        self.state = State::SelectReg;
+        reg.cmd
+            .write(|w| unsafe { w.bits(I2C_CMD_START_WITH_STOP) });
+        reg.irq_clr.write(|w| unsafe { w.bits(u32::MAX) });
+        reg.irq_enb.write(|w| {
+            w.idle()
+                .set_bit()
+                .arblost()
+                .set_bit()
+                .nackaddr()
+                .set_bit()
+                .nackdata()
+                .set_bit()
+        });
+        Ok(())
+    }
+
+    /// Advance the state machine. This needs to be called in the I2CA interrupt handler.
+    /// Returns [true] if the transfer is complete and the result is available with
+    /// [Self::poll].
+    pub fn on_interrupt(&mut self) -> bool {
+        let reg = regs();
+        let pending = reg.irq_end.read();
+        reg.irq_clr.write(|w| unsafe { w.bits(pending.bits()) });
+        let error = if pending.arblost().bit_is_set() {
+            Some(I2cError::ArbitrationLost)
+        } else if pending.nackaddr().bit_is_set() {
+            Some(I2cError::NackAddr)
+        } else if pending.nackdata().bit_is_set() {
+            Some(I2cError::NackData)
+        } else {
+            None
+        };
+        if let Some(e) = error {
+            if self.state != State::Idle {
+                self.finish(Err(e));
+                return true;
+            }
+        }
+        if pending.idle().bit_is_clear() {
+            return false;
+        }
+        match self.state {
+            State::Idle => false,
+            State::SelectReg => {
+                reg.fifo_clr.write(|w| w.rxfifo().set_bit());
+                reg.words.write(|w| unsafe { w.bits(2) });
+                reg.address.write(|w| unsafe {
+                    w.bits((self.sensor.address() as u32) << 1 | I2C_DIR_READ)
+                });
+                self.state = State::ReadTemp;
+                reg.cmd
+                    .write(|w| unsafe { w.bits(I2C_CMD_START_WITH_STOP) });
+                false
+            }
+            State::ReadTemp => {
+                let mut reply: [u8; 2] = [0; 2];
+                let mut read = 0;
+                while read < reply.len() && reg.status.read().rxnempty().bit_is_set() {
+                    reply[read] = reg.data.read().bits() as u8;
+                    read += 1;
+                }
+                let result = if read == reply.len() {
+                    Ok(Temperature::from_register(u16::from_be_bytes(reply)))
+                } else {
+                    Err(I2cError::Other(Error::InsufficientDataReceived))
+                };
+                self.finish(result);
+                true
+            }
+        }
+    }
+
+    fn finish(&mut self, result: Result<Temperature, I2cError<Error>>) {
+        let reg = regs();
+        reg.irq_enb.write(|w| unsafe { w.bits(0) });
+        reg.fifo_clr
+            .write(|w| w.rxfifo().set_bit().txfifo().set_bit());
+        self.state = State::Idle;
+        self.result = Some(result);
+    }
+
+    /// Fetch the result of the last read. Returns [nb::Error::WouldBlock] while the transfer is
+    /// in progress or if no read was started.
+    pub fn poll(&mut self) -> nb::Result<Temperature, I2cError<Error>> {
+        match self.result.take() {
+            Some(result) => result.map_err(nb::Error::Other),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Cancel a pending transfer, disable the I2CA interrupts and release the blocking driver
+    pub fn release(self) -> OnBoardAdt75 {
+        let reg = regs();
+        reg.irq_enb.write(|w| unsafe { w.bits(0) });
+        if self.is_busy() {
+            reg.cmd.write(|w| unsafe { w.bits(I2C_CMD_CANCEL) });
+            reg.fifo_clr
+                .write(|w| w.rxfifo().set_bit().txfifo().set_bit());
+        }
+        self.sensor
+    }
+}
+
+/// I2CA register block. The reader owns the I2CA master through the sensor driver, so the
+/// registers are not accessed by anybody else while the reader exists.
+fn regs() -> &'static i2ca::RegisterBlock {
+    // Safety: Only called by the reader, which owns the I2CA master
+    unsafe { &*I2CA::ptr() }
+}